@@ -0,0 +1,293 @@
+use nih_plug::params::persist::PersistentField;
+use nih_plug::prelude::ParamPtr;
+use nih_plug_vizia::vizia::prelude::Data;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU8, AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+
+use crate::device::KVPChannelPluginParams;
+use crate::loudness::{LoudnessMeter, LOUDNESS_SILENCE};
+use crate::preset::Preset;
+
+// Category of the snapshots, never shown in the preset browser
+const AB_CATEGORY: &str = "A/B";
+// Loudness matching never turns a slot down by more than this, in dB
+const MAX_MATCH_GAIN_DB: f32 = 24.0;
+/// How long the output fades out before and back in after switching slots, in seconds.
+pub const SWITCH_FADE_TIME: f32 = 0.01;
+
+/// One of the two complete sets of settings the editor can flip between.
+#[derive(Copy, Clone, PartialEq, Data, Serialize, Deserialize)]
+pub enum AbSlot {
+    A,
+    B,
+}
+
+impl AbSlot {
+    pub fn other(self) -> Self {
+        match self {
+            AbSlot::A => AbSlot::B,
+            AbSlot::B => AbSlot::A,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            AbSlot::A => "A",
+            AbSlot::B => "B",
+        }
+    }
+
+    fn index(self) -> usize {
+        match self {
+            AbSlot::A => 0,
+            AbSlot::B => 1,
+        }
+    }
+
+    fn from_index(index: usize) -> Self {
+        if index == 0 {
+            AbSlot::A
+        } else {
+            AbSlot::B
+        }
+    }
+}
+
+// Where the output is in the fade around a slot switch
+#[derive(Copy, Clone, PartialEq)]
+enum SwitchFade {
+    Idle,
+    FadingOut,
+    Muted,
+    FadingIn,
+}
+
+impl SwitchFade {
+    fn from_index(index: u8) -> Self {
+        match index {
+            1 => SwitchFade::FadingOut,
+            2 => SwitchFade::Muted,
+            3 => SwitchFade::FadingIn,
+            _ => SwitchFade::Idle,
+        }
+    }
+}
+
+/// The A/B comparison, shared between the editor and the plugin. The parameters always hold the
+/// active slot, the inactive one is kept here as a snapshot and restored with the session.
+///
+/// With loudness matching on, the louder slot is turned down to the quieter one's short-term
+/// loudness, as measured the last time each slot was switched away from.
+///
+/// Switching takes a handshake with the audio thread: the editor asks for the output to fade
+/// out, swaps the parameters once the audio thread reports it muted, and only lets it fade back
+/// in after every change has been applied. That way no block is heard with some parameters
+/// from either slot.
+pub struct AbState {
+    active: AtomicUsize,
+    loudness_match: AtomicBool,
+    // Loudness of each slot without the match gain, in LUFS as `f32` bits
+    loudness: [AtomicU32; 2],
+    // Applied to the plugin output by the audio thread, in dB as `f32` bits
+    match_gain_db: AtomicU32,
+    switch_fade: AtomicU8,
+    // Counts the audio thread's blocks, so the editor can tell whether it's running at all
+    processed_blocks: AtomicU32,
+    inactive: RwLock<Option<Preset>>,
+}
+
+// How the A/B state is stored in the session
+#[derive(Serialize, Deserialize)]
+struct AbSession {
+    active: AbSlot,
+    loudness_match: bool,
+    // JSON has no infinities, so slots without a reading are stored as `None`
+    loudness: [Option<f32>; 2],
+    inactive: Option<Preset>,
+}
+
+impl AbState {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self {
+            active: AtomicUsize::new(AbSlot::A.index()),
+            loudness_match: AtomicBool::new(false),
+            loudness: std::array::from_fn(|_| AtomicU32::new(LOUDNESS_SILENCE.to_bits())),
+            match_gain_db: AtomicU32::new(0.0f32.to_bits()),
+            switch_fade: AtomicU8::new(SwitchFade::Idle as u8),
+            processed_blocks: AtomicU32::new(0),
+            inactive: RwLock::new(None),
+        })
+    }
+
+    pub fn active(&self) -> AbSlot {
+        AbSlot::from_index(self.active.load(Ordering::Relaxed))
+    }
+
+    pub fn loudness_match(&self) -> bool {
+        self.loudness_match.load(Ordering::Relaxed)
+    }
+
+    /// The gain the audio thread applies to the output to match the slots' loudness, in dB.
+    pub fn match_gain_db(&self) -> f32 {
+        f32::from_bits(self.match_gain_db.load(Ordering::Relaxed))
+    }
+
+    fn switch_fade(&self) -> SwitchFade {
+        SwitchFade::from_index(self.switch_fade.load(Ordering::Acquire))
+    }
+
+    /// Asks the audio thread to fade the output out ahead of a switch.
+    pub fn begin_switch(&self) {
+        self.switch_fade.store(SwitchFade::FadingOut as u8, Ordering::Release);
+    }
+
+    /// Whether the audio thread has faded the output out, so the parameters can be swapped.
+    pub fn switch_muted(&self) -> bool {
+        self.switch_fade() == SwitchFade::Muted
+    }
+
+    /// Lets the audio thread fade the output back in after the parameters were swapped.
+    pub fn end_switch(&self) {
+        self.switch_fade.store(SwitchFade::FadingIn as u8, Ordering::Release);
+    }
+
+    /// Whether the audio thread should fade the output to silence.
+    pub fn switch_fade_out(&self) -> bool {
+        matches!(self.switch_fade(), SwitchFade::FadingOut | SwitchFade::Muted)
+    }
+
+    /// Called by the audio thread for every block it processes.
+    pub fn block_processed(&self) {
+        self.processed_blocks.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn processed_blocks(&self) -> u32 {
+        self.processed_blocks.load(Ordering::Relaxed)
+    }
+
+    /// Called by the audio thread once the output reached the gain `switch_fade_out` asked for.
+    /// The state only moves on if the editor didn't change it in the meantime.
+    pub fn finish_switch_fade(&self, faded_out: bool) {
+        let (from, to) = if faded_out {
+            (SwitchFade::FadingOut, SwitchFade::Muted)
+        } else {
+            (SwitchFade::FadingIn, SwitchFade::Idle)
+        };
+        let _ = self.switch_fade.compare_exchange(
+            from as u8,
+            to as u8,
+            Ordering::AcqRel,
+            Ordering::Relaxed,
+        );
+    }
+
+    /// Swaps the parameters for the other slot's snapshot. Returns the normalized value for
+    /// every parameter, for the editor to set as one gesture. A slot that was never filled
+    /// starts out as a copy of the other one.
+    pub fn switch(
+        &self,
+        slot: AbSlot,
+        params: &KVPChannelPluginParams,
+        meter: &LoudnessMeter,
+    ) -> Vec<(ParamPtr, f32)> {
+        let active = self.active();
+        if slot == active {
+            return Vec::new();
+        }
+        self.measure_active(meter);
+
+        let current = Preset::capture(active.name(), AB_CATEGORY, params);
+        let mut inactive = self.inactive.write().unwrap();
+        let values = inactive
+            .as_ref()
+            .unwrap_or(&current)
+            .normalized_values(params);
+        *inactive = Some(current);
+        drop(inactive);
+
+        self.active.store(slot.index(), Ordering::Relaxed);
+        self.update_match_gain();
+        values
+    }
+
+    /// Overwrites the inactive slot with the current settings.
+    pub fn copy_to_inactive(&self, params: &KVPChannelPluginParams, meter: &LoudnessMeter) {
+        let active = self.active();
+        *self.inactive.write().unwrap() =
+            Some(Preset::capture(active.other().name(), AB_CATEGORY, params));
+
+        // Both slots sound the same now
+        self.measure_active(meter);
+        self.store_loudness(active.other(), self.loudness(active));
+        self.update_match_gain();
+    }
+
+    pub fn set_loudness_match(&self, loudness_match: bool, meter: &LoudnessMeter) {
+        self.measure_active(meter);
+        self.loudness_match.store(loudness_match, Ordering::Relaxed);
+        self.update_match_gain();
+    }
+
+    fn loudness(&self, slot: AbSlot) -> f32 {
+        f32::from_bits(self.loudness[slot.index()].load(Ordering::Relaxed))
+    }
+
+    fn store_loudness(&self, slot: AbSlot, lufs: f32) {
+        self.loudness[slot.index()].store(lufs.to_bits(), Ordering::Relaxed);
+    }
+
+    // Silence is skipped so pausing playback doesn't throw away the last reading
+    fn measure_active(&self, meter: &LoudnessMeter) {
+        let short_term = meter.short_term();
+        if short_term.is_finite() {
+            self.store_loudness(self.active(), short_term - self.match_gain_db());
+        }
+    }
+
+    // Only ever turns down, so switching to the louder slot can't push the output into clipping
+    fn update_match_gain(&self) {
+        let active = self.active();
+        let (own, other) = (self.loudness(active), self.loudness(active.other()));
+        let gain_db = if self.loudness_match() && own.is_finite() && other.is_finite() {
+            (other - own).clamp(-MAX_MATCH_GAIN_DB, 0.0)
+        } else {
+            0.0
+        };
+        self.match_gain_db.store(gain_db.to_bits(), Ordering::Relaxed);
+    }
+}
+
+impl<'a> PersistentField<'a, String> for Arc<AbState> {
+    fn set(&self, new_value: String) {
+        // Sessions with unreadable A/B state still load, just without the inactive slot
+        let Ok(session) = serde_json::from_str::<AbSession>(&new_value) else {
+            return;
+        };
+
+        self.active.store(session.active.index(), Ordering::Relaxed);
+        self.loudness_match.store(session.loudness_match, Ordering::Relaxed);
+        for slot in [AbSlot::A, AbSlot::B] {
+            let lufs = session.loudness[slot.index()].unwrap_or(LOUDNESS_SILENCE);
+            self.store_loudness(slot, lufs);
+        }
+        *self.inactive.write().unwrap() = session.inactive;
+        self.update_match_gain();
+    }
+
+    fn map<F, R>(&self, f: F) -> R
+    where
+        F: Fn(&String) -> R,
+    {
+        let session = AbSession {
+            active: self.active(),
+            loudness_match: self.loudness_match(),
+            loudness: [AbSlot::A, AbSlot::B].map(|slot| {
+                let lufs = self.loudness(slot);
+                lufs.is_finite().then_some(lufs)
+            }),
+            inactive: self.inactive.read().unwrap().clone(),
+        };
+        f(&serde_json::to_string(&session).unwrap_or_default())
+    }
+}
@@ -0,0 +1,186 @@
+use nih_plug_vizia::vizia::prelude::*;
+use nih_plug_vizia::widgets::RawParamEvent;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::ab::AbSlot;
+use crate::device::KVPChannelPluginParams;
+use crate::loudness::LoudnessMeter;
+
+// How often a pending switch checks whether the audio thread has muted the output
+const SWITCH_POLL: Duration = Duration::from_millis(5);
+// Switch anyway once the audio thread hasn't processed a block for this many polls, as the
+// host isn't processing audio then. Well past the period of the largest buffers hosts use.
+const SWITCH_STALL_POLLS: u32 = 100;
+
+// A switch waiting for the output to fade out, or for its parameter changes to be applied
+struct PendingSwitch {
+    slot: AbSlot,
+    // The audio thread's block count at the last poll, and how many polls it hasn't moved
+    processed_blocks: u32,
+    stalled_polls: u32,
+    swapped: bool,
+}
+
+/// The editor's side of the A/B comparison, mirroring the plugin's `AbState` for the header.
+#[derive(Lens)]
+pub struct AbSwitch {
+    #[lens(ignore)]
+    params: Arc<KVPChannelPluginParams>,
+    #[lens(ignore)]
+    loudness: Arc<LoudnessMeter>,
+    active: AbSlot,
+    loudness_match: bool,
+    #[lens(ignore)]
+    pending: Option<PendingSwitch>,
+}
+
+pub enum AbEvent {
+    Select(AbSlot),
+    CopyToInactive,
+    ToggleLoudnessMatch,
+    PollSwitch,
+    FinishSwitch,
+}
+
+impl AbSwitch {
+    pub fn new(params: Arc<KVPChannelPluginParams>, loudness: Arc<LoudnessMeter>) -> Self {
+        Self {
+            active: params.ab_state.active(),
+            loudness_match: params.ab_state.loudness_match(),
+            params,
+            loudness,
+            pending: None,
+        }
+    }
+
+    // The output fades out first, the parameters only change once it's muted
+    fn request_switch(&mut self, slot: AbSlot) {
+        if slot != self.active && self.pending.is_none() {
+            self.params.ab_state.begin_switch();
+            self.pending = Some(PendingSwitch {
+                slot,
+                processed_blocks: self.params.ab_state.processed_blocks(),
+                stalled_polls: 0,
+                swapped: false,
+            });
+        }
+    }
+
+    fn poll_switch(&mut self, cx: &mut EventContext) {
+        let Some(pending) = self.pending.as_mut().filter(|pending| !pending.swapped) else {
+            return;
+        };
+        let processed_blocks = self.params.ab_state.processed_blocks();
+        if processed_blocks == pending.processed_blocks {
+            pending.stalled_polls += 1;
+        } else {
+            pending.processed_blocks = processed_blocks;
+            pending.stalled_polls = 0;
+        }
+
+        if self.params.ab_state.switch_muted() || pending.stalled_polls >= SWITCH_STALL_POLLS {
+            pending.swapped = true;
+            let slot = pending.slot;
+            self.select(cx, slot);
+            // Queued behind the parameter changes, so the output only fades back in once
+            // they've all been applied
+            cx.emit(AbEvent::FinishSwitch);
+        }
+    }
+
+    fn finish_switch(&mut self) {
+        if self.pending.take().is_some() {
+            self.params.ab_state.end_switch();
+        }
+    }
+
+    /// Every parameter changes within one gesture while the output is muted, so neither the
+    /// host's automation nor the audio ever has a mix of both slots.
+    fn select(&mut self, cx: &mut EventContext, slot: AbSlot) {
+        let values = self.params.ab_state.switch(slot, &self.params, &self.loudness);
+        for (param_ptr, _) in &values {
+            cx.emit(RawParamEvent::BeginSetParameter(*param_ptr));
+        }
+        for (param_ptr, normalized) in &values {
+            cx.emit(RawParamEvent::SetParameterNormalized(*param_ptr, *normalized));
+        }
+        for (param_ptr, _) in &values {
+            cx.emit(RawParamEvent::EndSetParameter(*param_ptr));
+        }
+
+        self.active = self.params.ab_state.active();
+    }
+}
+
+impl Model for AbSwitch {
+    fn event(&mut self, cx: &mut EventContext, event: &mut Event) {
+        event.map(|ab_event, _| match *ab_event {
+            AbEvent::Select(slot) => self.request_switch(slot),
+            AbEvent::PollSwitch => self.poll_switch(cx),
+            AbEvent::FinishSwitch => self.finish_switch(),
+            AbEvent::CopyToInactive => {
+                self.params.ab_state.copy_to_inactive(&self.params, &self.loudness);
+            }
+            AbEvent::ToggleLoudnessMatch => {
+                self.loudness_match = !self.loudness_match;
+                self.params
+                    .ab_state
+                    .set_loudness_match(self.loudness_match, &self.loudness);
+            }
+        });
+    }
+}
+
+// Closing the editor mid-switch must not leave the output muted
+impl Drop for AbSwitch {
+    fn drop(&mut self) {
+        self.finish_switch();
+    }
+}
+
+/// The header's A/B buttons: one per slot, copying the active slot over the other one and
+/// toggling loudness matching.
+pub fn ab_bar(cx: &mut Context) -> Handle<HStack> {
+    HStack::new(cx, |cx| {
+        let timer = cx.add_timer(SWITCH_POLL, None, |cx, action| {
+            if let TimerAction::Tick(_) = action {
+                cx.emit(AbEvent::PollSwitch);
+            }
+        });
+        cx.start_timer(timer);
+
+        for slot in [AbSlot::A, AbSlot::B] {
+            Label::new(cx, slot.name())
+                .class("button")
+                .toggle_class("selected", AbSwitch::active.map(move |active| *active == slot))
+                .width(Pixels(22.0))
+                .text_align(TextAlign::Center)
+                .cursor(CursorIcon::Hand)
+                .on_press(move |cx| cx.emit(AbEvent::Select(slot)));
+        }
+
+        Label::new(
+            cx,
+            AbSwitch::active.map(|active| format!("COPY TO {}", active.other().name())),
+        )
+        .class("button")
+        .width(Pixels(64.0))
+        .text_align(TextAlign::Center)
+        .cursor(CursorIcon::Hand)
+        .on_press(|cx| cx.emit(AbEvent::CopyToInactive));
+
+        Label::new(cx, "MATCH")
+            .class("button")
+            .toggle_class("selected", AbSwitch::loudness_match)
+            .width(Pixels(48.0))
+            .text_align(TextAlign::Center)
+            .cursor(CursorIcon::Hand)
+            .on_press(|cx| cx.emit(AbEvent::ToggleLoudnessMatch));
+    })
+    .width(Auto)
+    .height(Auto)
+    .col_between(Pixels(4.0))
+    .child_top(Stretch(1.0))
+    .child_bottom(Stretch(1.0))
+}
@@ -0,0 +1,214 @@
+use nih_plug::prelude::Enum;
+
+// Below this input difference the ADAA quotients become ill-conditioned and the
+// midpoint fallbacks are used instead
+const ADAA_EPSILON: f64 = 1.0e-5;
+
+#[derive(Copy, Clone, Enum, PartialEq)]
+pub enum AdaaOrder {
+    Off,
+    #[name = "1st Order"]
+    FirstOrder,
+    #[name = "2nd Order"]
+    SecondOrder,
+}
+
+/// A static waveshaping curve together with its first and second antiderivatives, as needed
+/// for antiderivative anti-aliasing. Both antiderivatives are taken to be zero at `x = 0`.
+pub trait Antiderivative {
+    fn value(&self, x: f64) -> f64;
+    fn first(&self, x: f64) -> f64;
+    fn second(&self, x: f64) -> f64;
+}
+
+/// Antiderivative anti-aliasing state for a single channel. The first order variant delays
+/// the signal by half a sample, the second order variant by a full sample.
+pub struct AdaaState {
+    x1: f64,
+    x2: f64,
+    first_x1: f64,
+    second_x1: f64,
+    d1_prev: f64,
+}
+
+impl AdaaState {
+    pub fn new() -> Self {
+        Self {
+            x1: 0.0,
+            x2: 0.0,
+            first_x1: 0.0,
+            second_x1: 0.0,
+            d1_prev: 0.0,
+        }
+    }
+
+    pub fn process_first_order(&mut self, x: f64, curve: &impl Antiderivative) -> f64 {
+        let first_x = curve.first(x);
+        let diff = x - self.x1;
+
+        let output = if diff.abs() < ADAA_EPSILON {
+            curve.value(0.5 * (x + self.x1))
+        } else {
+            (first_x - self.first_x1) / diff
+        };
+
+        self.x2 = self.x1;
+        self.x1 = x;
+        self.first_x1 = first_x;
+        output
+    }
+
+    pub fn process_second_order(&mut self, x: f64, curve: &impl Antiderivative) -> f64 {
+        let second_x = curve.second(x);
+        let diff = x - self.x1;
+
+        let d1 = if diff.abs() < ADAA_EPSILON {
+            curve.first(0.5 * (x + self.x1))
+        } else {
+            (second_x - self.second_x1) / diff
+        };
+
+        let diff2 = x - self.x2;
+        let output = if diff2.abs() < ADAA_EPSILON {
+            // The input turned around, evaluate around the midpoint of the outer samples
+            let x_bar = 0.5 * (x + self.x2);
+            let delta = x_bar - self.x1;
+            if delta.abs() < ADAA_EPSILON {
+                curve.value(0.5 * (x_bar + self.x1))
+            } else {
+                (2.0 / delta)
+                    * (curve.first(x_bar) + (self.second_x1 - curve.second(x_bar)) / delta)
+            }
+        } else {
+            2.0 * (d1 - self.d1_prev) / diff2
+        };
+
+        self.x2 = self.x1;
+        self.x1 = x;
+        self.second_x1 = second_x;
+        self.d1_prev = d1;
+        output
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+}
+
+impl Default for AdaaState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Tabulated first and second antiderivatives for curves without a practical closed form.
+/// Values are interpolated with cubic Hermite splines since the derivatives at every node
+/// are known exactly. Past the end of the table the curve is assumed to have settled to a
+/// constant, which holds for anything that saturates.
+pub struct AntiderivativeTable {
+    step: f64,
+    range: f64,
+    // Whether the tabulated curve is odd (`true`) or even (`false`)
+    odd: bool,
+    values: Vec<f64>,
+    first: Vec<f64>,
+    second: Vec<f64>,
+}
+
+impl AntiderivativeTable {
+    pub fn new(f: impl Fn(f64) -> f64, odd: bool, range: f64, size: usize) -> Self {
+        let step = range / size as f64;
+        let values: Vec<f64> = (0..=size).map(|i| f(i as f64 * step)).collect();
+
+        let mut first = vec![0.0; size + 1];
+        let mut second = vec![0.0; size + 1];
+        for i in 0..size {
+            // Simpson's rule for the first integral, the exact integral of the Hermite
+            // spline through the first antiderivative for the second one
+            let mid = f((i as f64 + 0.5) * step);
+            first[i + 1] = first[i] + step / 6.0 * (values[i] + 4.0 * mid + values[i + 1]);
+            second[i + 1] = second[i]
+                + step / 2.0 * (first[i] + first[i + 1])
+                + step * step / 12.0 * (values[i] - values[i + 1]);
+        }
+
+        Self {
+            step,
+            range,
+            odd,
+            values,
+            first,
+            second,
+        }
+    }
+
+    fn hermite(p0: f64, p1: f64, m0: f64, m1: f64, h: f64, t: f64) -> f64 {
+        let t2 = t * t;
+        let t3 = t2 * t;
+        (2.0 * t3 - 3.0 * t2 + 1.0) * p0
+            + (t3 - 2.0 * t2 + t) * h * m0
+            + (-2.0 * t3 + 3.0 * t2) * p1
+            + (t3 - t2) * h * m1
+    }
+
+    fn first_abs(&self, u: f64) -> f64 {
+        let last = self.values.len() - 1;
+        if u >= self.range {
+            return self.first[last] + self.values[last] * (u - self.range);
+        }
+
+        let pos = u / self.step;
+        let i = (pos as usize).min(last - 1);
+        let t = pos - i as f64;
+        Self::hermite(
+            self.first[i],
+            self.first[i + 1],
+            self.values[i],
+            self.values[i + 1],
+            self.step,
+            t,
+        )
+    }
+
+    fn second_abs(&self, u: f64) -> f64 {
+        let last = self.values.len() - 1;
+        if u >= self.range {
+            let over = u - self.range;
+            return self.second[last]
+                + self.first[last] * over
+                + self.values[last] * over * over / 2.0;
+        }
+
+        let pos = u / self.step;
+        let i = (pos as usize).min(last - 1);
+        let t = pos - i as f64;
+        Self::hermite(
+            self.second[i],
+            self.second[i + 1],
+            self.first[i],
+            self.first[i + 1],
+            self.step,
+            t,
+        )
+    }
+
+    /// The first antiderivative, even for odd curves and odd for even curves.
+    pub fn first(&self, x: f64) -> f64 {
+        let value = self.first_abs(x.abs());
+        if self.odd || x >= 0.0 {
+            value
+        } else {
+            -value
+        }
+    }
+
+    /// The second antiderivative, with the opposite symmetry of the first one.
+    pub fn second(&self, x: f64) -> f64 {
+        let value = self.second_abs(x.abs());
+        if !self.odd || x >= 0.0 {
+            value
+        } else {
+            -value
+        }
+    }
+}
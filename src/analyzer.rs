@@ -0,0 +1,288 @@
+use nih_plug::buffer::Buffer;
+use nih_plug::prelude::*;
+use std::f32::consts::PI;
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+
+// Samples of history each tap keeps, enough for the largest FFT. Must be a power of two
+const TAP_RING_SIZE: usize = 16384;
+// Spectra are tilted around this frequency
+const SLOPE_PIVOT_HZ: f32 = 1000.0;
+// Floor for bins that have never seen any signal
+const SPECTRUM_FLOOR_DB: f32 = -150.0;
+
+#[derive(Copy, Clone, Enum, PartialEq)]
+pub enum AnalyzerResolution {
+    #[name = "1024"]
+    Fft1024,
+    #[name = "2048"]
+    Fft2048,
+    #[name = "4096"]
+    Fft4096,
+    #[name = "8192"]
+    Fft8192,
+}
+
+impl AnalyzerResolution {
+    pub fn fft_size(&self) -> usize {
+        match self {
+            AnalyzerResolution::Fft1024 => 1024,
+            AnalyzerResolution::Fft2048 => 2048,
+            AnalyzerResolution::Fft4096 => 4096,
+            AnalyzerResolution::Fft8192 => 8192,
+        }
+    }
+}
+
+/// Display settings for the spectrum analyzer. None of these affect the audio so they're
+/// kept out of the hosts' automation lanes.
+#[derive(Params)]
+pub struct AnalyzerParams {
+    #[id = "analyzer_resolution"]
+    pub resolution: EnumParam<AnalyzerResolution>,
+    #[id = "analyzer_averaging"]
+    pub averaging: FloatParam,
+    #[id = "analyzer_slope"]
+    pub slope: FloatParam,
+    #[id = "analyzer_peak_hold"]
+    pub peak_hold: BoolParam,
+    #[id = "analyzer_freeze"]
+    pub freeze: BoolParam,
+}
+
+impl AnalyzerParams {
+    pub fn new() -> Self {
+        Self {
+            resolution: EnumParam::new("Analyzer:Resolution", AnalyzerResolution::Fft4096)
+                .non_automatable(),
+            averaging: FloatParam::new(
+                "Analyzer:Averaging",
+                0.7,
+                FloatRange::Linear {
+                    min: 0.0,
+                    max: 0.95,
+                },
+            )
+            .with_unit("%")
+            .with_value_to_string(formatters::v2s_f32_percentage(0))
+            .with_string_to_value(formatters::s2v_f32_percentage())
+            .non_automatable(),
+            slope: FloatParam::new(
+                "Analyzer:Slope",
+                4.5,
+                FloatRange::Linear {
+                    min: 0.0,
+                    max: 6.0,
+                },
+            )
+            .with_unit(" dB/oct")
+            .with_step_size(0.5)
+            .non_automatable(),
+            peak_hold: BoolParam::new("Analyzer:Peak Hold", false).non_automatable(),
+            freeze: BoolParam::new("Analyzer:Freeze", false).non_automatable(),
+        }
+    }
+}
+
+/// A lock-free history of a mono mixdown of the signal at one point in the chain. The audio
+/// thread writes, the editor reads whatever is newest.
+pub struct SpectrumTap {
+    ring: Vec<AtomicU32>,
+    write_pos: AtomicUsize,
+}
+
+impl SpectrumTap {
+    pub fn new() -> Self {
+        Self {
+            ring: (0..TAP_RING_SIZE).map(|_| AtomicU32::new(0)).collect(),
+            write_pos: AtomicUsize::new(0),
+        }
+    }
+
+    /// Appends a block of audio. Called from the audio thread only.
+    pub fn write(&self, buffer: &Buffer) {
+        let channels = buffer.as_slice_immutable();
+        if channels.is_empty() {
+            return;
+        }
+
+        let scale = 1.0 / channels.len() as f32;
+        let mut pos = self.write_pos.load(Ordering::Relaxed);
+        for sample_idx in 0..buffer.samples() {
+            let mono = channels.iter().map(|channel| channel[sample_idx]).sum::<f32>() * scale;
+            self.ring[pos].store(mono.to_bits(), Ordering::Relaxed);
+            pos = (pos + 1) & (TAP_RING_SIZE - 1);
+        }
+        self.write_pos.store(pos, Ordering::Release);
+    }
+
+    /// Copies the newest `output.len()` samples, oldest first.
+    fn read_latest(&self, output: &mut [f32]) {
+        let end = self.write_pos.load(Ordering::Acquire);
+        let start = end.wrapping_sub(output.len());
+        for (idx, sample) in output.iter_mut().enumerate() {
+            let pos = start.wrapping_add(idx) & (TAP_RING_SIZE - 1);
+            *sample = f32::from_bits(self.ring[pos].load(Ordering::Relaxed));
+        }
+    }
+}
+
+impl Default for SpectrumTap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// In-place iterative radix-2 FFT, `re.len()` must be a power of two
+fn fft(re: &mut [f32], im: &mut [f32]) {
+    let n = re.len();
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let mut len = 2;
+    while len <= n {
+        let angle = -2.0 * PI / len as f32;
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let (w_im, w_re) = (angle * k as f32).sin_cos();
+                let (a, b) = (start + k, start + k + len / 2);
+                let t_re = re[b] * w_re - im[b] * w_im;
+                let t_im = re[b] * w_im + im[b] * w_re;
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+            }
+        }
+        len <<= 1;
+    }
+}
+
+/// The editor's side of the analyzer. Turns a tap's history into an averaged spectrum in dB,
+/// optionally keeping the highest level every bin has reached.
+pub struct Spectrum {
+    fft_size: usize,
+    window: Vec<f32>,
+    re: Vec<f32>,
+    im: Vec<f32>,
+    // Averaged power per bin
+    power: Vec<f32>,
+    peak_db: Vec<f32>,
+    peak_hold: bool,
+}
+
+impl Spectrum {
+    pub fn new() -> Self {
+        let mut spectrum = Self {
+            fft_size: 0,
+            window: Vec::new(),
+            re: Vec::new(),
+            im: Vec::new(),
+            power: Vec::new(),
+            peak_db: Vec::new(),
+            peak_hold: false,
+        };
+        spectrum.resize(AnalyzerResolution::Fft4096.fft_size());
+        spectrum
+    }
+
+    fn resize(&mut self, fft_size: usize) {
+        self.fft_size = fft_size;
+        // Hann window
+        self.window = (0..fft_size)
+            .map(|idx| 0.5 - 0.5 * (2.0 * PI * idx as f32 / fft_size as f32).cos())
+            .collect();
+        self.re = vec![0.0; fft_size];
+        self.im = vec![0.0; fft_size];
+        self.power = vec![0.0; fft_size / 2];
+        self.peak_db = vec![SPECTRUM_FLOOR_DB; fft_size / 2];
+    }
+
+    /// Analyzes the newest samples of `tap` unless the analyzer is frozen.
+    pub fn update(&mut self, tap: &SpectrumTap, params: &AnalyzerParams) {
+        let fft_size = params.resolution.value().fft_size();
+        if fft_size != self.fft_size {
+            self.resize(fft_size);
+        }
+        let peak_hold = params.peak_hold.value();
+        if peak_hold && !self.peak_hold {
+            self.peak_db.iter_mut().for_each(|peak| *peak = SPECTRUM_FLOOR_DB);
+        }
+        self.peak_hold = peak_hold;
+        if params.freeze.value() {
+            return;
+        }
+
+        tap.read_latest(&mut self.re);
+        for (sample, window) in self.re.iter_mut().zip(self.window.iter()) {
+            *sample *= window;
+        }
+        self.im.iter_mut().for_each(|sample| *sample = 0.0);
+        fft(&mut self.re, &mut self.im);
+
+        // Scaled so a full scale sine reads 0 dB, the Hann window halves the amplitude
+        let scale = 4.0 / self.fft_size as f32;
+        let averaging = params.averaging.value();
+        for (bin, power) in self.power.iter_mut().enumerate() {
+            let magnitude = self.re[bin].hypot(self.im[bin]) * scale;
+            *power = magnitude * magnitude + (*power - magnitude * magnitude) * averaging;
+            if self.peak_hold {
+                let db = util::gain_to_db_fast(power.sqrt().max(util::MINUS_INFINITY_GAIN));
+                self.peak_db[bin] = self.peak_db[bin].max(db);
+            }
+        }
+    }
+
+    fn bin_range(&self, low_hz: f32, high_hz: f32, sample_rate: f32) -> std::ops::Range<usize> {
+        let bin_hz = sample_rate / self.fft_size as f32;
+        let num_bins = self.power.len();
+        let low = ((low_hz / bin_hz).round() as usize).min(num_bins - 1);
+        let high = ((high_hz / bin_hz).round() as usize).clamp(low + 1, num_bins);
+        low..high
+    }
+
+    fn tilt_db(frequency: f32, params: &AnalyzerParams) -> f32 {
+        params.slope.value() * (frequency / SLOPE_PIVOT_HZ).log2()
+    }
+
+    /// The level in dB of the loudest bin between `low_hz` and `high_hz`, with the slope
+    /// applied.
+    pub fn level_db(&self, low_hz: f32, high_hz: f32, sample_rate: f32, params: &AnalyzerParams) -> f32 {
+        let power = self.power[self.bin_range(low_hz, high_hz, sample_rate)]
+            .iter()
+            .fold(0.0f32, |max, &power| max.max(power));
+        util::gain_to_db_fast(power.sqrt().max(util::MINUS_INFINITY_GAIN))
+            + Self::tilt_db(low_hz, params)
+    }
+
+    pub fn peak_hold(&self) -> bool {
+        self.peak_hold
+    }
+
+    /// Like `level_db()`, but for the held peaks. Returns `None` when peak hold is off.
+    pub fn peak_db(&self, low_hz: f32, high_hz: f32, sample_rate: f32, params: &AnalyzerParams) -> Option<f32> {
+        if !self.peak_hold {
+            return None;
+        }
+        let peak = self.peak_db[self.bin_range(low_hz, high_hz, sample_rate)]
+            .iter()
+            .fold(SPECTRUM_FLOOR_DB, |max, &peak| max.max(peak));
+        Some(peak + Self::tilt_db(low_hz, params))
+    }
+}
+
+impl Default for Spectrum {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,7 +1,28 @@
 use nih_plug::prelude::Enum;
+use std::sync::OnceLock;
+
+use crate::adaa::{AdaaOrder, AdaaState, Antiderivative, AntiderivativeTable};
+use crate::dc_blocker::DcBlocker;
+use crate::emphasis::{Emphasis, EMPHASIS_MAX_TILT_DB};
+use crate::exciter::Exciter;
+use crate::lofi::LoFi;
+use crate::saturation::{DiodeClipper, Transformer};
+use crate::tape::TapeMachine;
+
+// Antiderivatives of tanh(x) and tanh(x^2) for the tanh based curves, shared by all instances
+static TANH_TABLE: OnceLock<AntiderivativeTable> = OnceLock::new();
+static SQUARED_TANH_TABLE: OnceLock<AntiderivativeTable> = OnceLock::new();
+
+fn tanh_table() -> &'static AntiderivativeTable {
+    TANH_TABLE.get_or_init(|| AntiderivativeTable::new(f64::tanh, true, 10.0, 2048))
+}
+
+fn squared_tanh_table() -> &'static AntiderivativeTable {
+    SQUARED_TANH_TABLE.get_or_init(|| AntiderivativeTable::new(|x| (x * x).tanh(), false, 4.0, 1024))
+}
 
 pub struct Colorizer {
-    pub intensity: f32,      // Controls overall effect intensity (0.0 - 1.0)
+    pub intensity: f32,      // Controls the level dependent coloration (0.0 - 1.0)
     pub color_type: ColorType,
     pub sample_rate: f32,
     envelope: f32,
@@ -9,15 +30,37 @@ pub struct Colorizer {
     prev_excess_db: f32,     // Store previous excess for effect detection
     attack_coeff: f32,       // Intentionally exaggerated coefficient
     release_coeff: f32,      // Intentionally exaggerated coefficient
-    drive: f32,              // Internal drive parameter (derived from intensity)
-    saturation: f32,         // Saturation amount
+    drive: f32,              // Linear gain into the saturation
+    saturation: f32,         // Saturation amount (0.0 - 0.4)
+    adaa_order: AdaaOrder,   // Anti-aliasing applied to the saturation curve
+    adaa: AdaaState,
+    tone: f32,               // Color Tone, tilts the saturation dark or bright (-1.0 - 1.0)
+    emphasis: Emphasis,
+    tolerances: ComponentTolerances, // This channel's fixed deviation from the nominal values
+    variance: f32,           // How much of the tolerances is applied (0.0 - 1.0)
+    variance_drive: f32,     // Drive offset from the tolerances as linear gain
+    dc_blocker: DcBlocker,   // Removes the offset left by asymmetric curves and even harmonics
+    pub tape: TapeMachine,   // The Tape model's full machine, not just its saturation
+    transformer: Transformer,
+    diode: DiodeClipper,
+    pub exciter: Exciter,
+    pub lofi: LoFi,
 }
 
 #[derive(Copy, Clone, Enum, PartialEq)]
 pub enum ColorType {
-    Warm,     // Gentle tape-like saturation
-    Bright,   // More aggressive with high-end emphasis
-    Vintage,  // More midrange focused coloration
+    Warm,         // Gentle tape-like saturation
+    Bright,       // More aggressive with high-end emphasis
+    Vintage,      // More midrange focused coloration
+    Tube,         // Biased triode stage, even harmonics grow with drive
+    Tape,         // Tape machine with hysteresis, head bump and wow/flutter
+    Transformer,  // Core saturation that hits the low end first
+    Diode,        // Hard-ish diode clipper
+    #[name = "FET"]
+    Fet,          // Square-law FET stage, mostly second harmonic
+    Exciter,      // Band-limited 2nd/3rd harmonics for air
+    #[name = "Lo-Fi"]
+    LoFi,         // Bit crushing, decimation, telephone band and crackle
 }
 
 impl Colorizer {
@@ -32,6 +75,10 @@ impl Colorizer {
             sample_rate
         );
 
+        // Build the lookup tables up front so it doesn't happen on the audio thread
+        tanh_table();
+        squared_tanh_table();
+
         Colorizer {
             intensity: 0.5,
             color_type,
@@ -42,7 +89,20 @@ impl Colorizer {
             attack_coeff,
             release_coeff,
             drive: 1.0,
-            saturation: 0.2,
+            saturation: 0.0,
+            adaa_order: AdaaOrder::Off,
+            adaa: AdaaState::new(),
+            tone: 0.0,
+            emphasis: Emphasis::new(sample_rate),
+            tolerances: ComponentTolerances::from_seed(0),
+            variance: 0.0,
+            variance_drive: 1.0,
+            dc_blocker: DcBlocker::new(sample_rate),
+            tape: TapeMachine::new(sample_rate),
+            transformer: Transformer::new(sample_rate),
+            diode: DiodeClipper::new(sample_rate),
+            exciter: Exciter::new(sample_rate),
+            lofi: LoFi::new(sample_rate),
         }
     }
 
@@ -56,6 +116,13 @@ impl Colorizer {
             ColorType::Warm => 0.008,     // 8ms - gentler attack
             ColorType::Bright => 0.002,   // 2ms - fast attack for brightness
             ColorType::Vintage => 0.015,  // 15ms - slower attack for vintage feel
+            ColorType::Tube => 0.01,      // 10ms - soft, rounded attack
+            ColorType::Tape => 0.02,      // 20ms - tape smooths transients
+            ColorType::Transformer => 0.012, // 12ms - follows the low end
+            ColorType::Diode => 0.001,    // 1ms - diodes react instantly
+            ColorType::Fet => 0.004,      // 4ms - fast FET response
+            ColorType::Exciter => 0.002,  // 2ms - only used for the level detection
+            ColorType::LoFi => 0.005,     // 5ms
         }
     }
     
@@ -64,6 +131,36 @@ impl Colorizer {
             ColorType::Warm => 0.12,     // 120ms - smooth release
             ColorType::Bright => 0.06,   // 60ms - faster release
             ColorType::Vintage => 0.2,   // 200ms - longer release for more 'glue'
+            ColorType::Tube => 0.15,     // 150ms - natural bloom
+            ColorType::Tape => 0.25,     // 250ms - slow tape compression
+            ColorType::Transformer => 0.18, // 180ms - lingering low end weight
+            ColorType::Diode => 0.05,    // 50ms - snaps back quickly
+            ColorType::Fet => 0.08,      // 80ms - punchy release
+            ColorType::Exciter => 0.06,  // 60ms
+            ColorType::LoFi => 0.1,      // 100ms
+        }
+    }
+
+    // Built in emphasis of each color type, the Color Tone control is added on top
+    pub fn emphasis_tilt(color: ColorType) -> f32 {
+        match color {
+            ColorType::Warm => -3.0,      // saturate the low mids harder
+            ColorType::Bright => 4.0,     // push the top end into the curve
+            ColorType::Vintage => -1.5,
+            ColorType::Tube => 0.0,
+            ColorType::Tape => 0.0,       // the tape machine has its own EQ
+            ColorType::Transformer => 0.0, // already frequency dependent
+            ColorType::Diode => 0.0,
+            ColorType::Fet => 1.5,
+            ColorType::Exciter => 0.0,    // has its own highpass
+            ColorType::LoFi => 0.0,
+        }
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        if self.sample_rate != sample_rate {
+            self.sample_rate = sample_rate;
+            self.update_coefficients();
         }
     }
 
@@ -74,27 +171,80 @@ impl Colorizer {
         }
     }
 
+    pub fn set_adaa_order(&mut self, order: AdaaOrder) {
+        if self.adaa_order != order {
+            self.adaa_order = order;
+            self.adaa.reset();
+        }
+    }
+
+    /// Sets the Color Tone from -1.0 (dark) to 1.0 (bright).
+    pub fn set_tone(&mut self, tone: f32) {
+        self.tone = tone.clamp(-1.0, 1.0);
+        self.emphasis.set_tilt(
+            Self::emphasis_tilt(self.color_type) + self.tone * EMPHASIS_MAX_TILT_DB,
+        );
+    }
+
+    /// Picks this channel's component tolerances. The same seed always gives the same
+    /// deviations so a session recalls identically.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.tolerances = ComponentTolerances::from_seed(seed);
+        self.update_coefficients();
+    }
+
+    /// Sets how far this channel strays from the nominal drive, threshold and timing, from
+    /// 0.0 to 1.0.
+    pub fn set_variance(&mut self, variance: f32) {
+        let variance = variance.clamp(0.0, 1.0);
+        if self.variance != variance {
+            self.variance = variance;
+            self.update_coefficients();
+        }
+    }
+
     pub fn set_intensity(&mut self, intensity: f32) {
         self.intensity = intensity.clamp(0.0, 1.0);
-        // Update derived parameters
-        self.drive = 1.0 + (intensity * 3.0); // 1.0 to 4.0
-        self.saturation = intensity * 0.4;    // 0.0 to 0.4
+    }
+
+    /// Sets the linear gain applied before the saturation.
+    pub fn set_drive(&mut self, drive: f32) {
+        self.drive = drive.max(0.0);
+    }
+
+    /// Sets the saturation amount from 0.0 to 1.0. The curves are voiced for an internal
+    /// range of 0.0 to 0.4.
+    pub fn set_saturation(&mut self, saturation: f32) {
+        self.saturation = saturation.clamp(0.0, 1.0) * 0.4;
     }
 
     fn update_coefficients(&mut self) {
+        self.emphasis.set_sample_rate(self.sample_rate);
+        self.dc_blocker.set_sample_rate(self.sample_rate);
+        self.emphasis.set_tilt(
+            Self::emphasis_tilt(self.color_type) + self.tone * EMPHASIS_MAX_TILT_DB,
+        );
+        self.tape.set_sample_rate(self.sample_rate);
+        self.transformer.set_sample_rate(self.sample_rate);
+        self.diode.set_sample_rate(self.sample_rate);
+        self.exciter.set_sample_rate(self.sample_rate);
+        self.lofi.set_sample_rate(self.sample_rate);
+        self.variance_drive =
+            db_to_linear(self.tolerances.drive * self.variance * VARIANCE_MAX_DRIVE_DB);
+        let timing = 1.0 + self.tolerances.timing * self.variance * VARIANCE_MAX_TIMING;
         self.attack_coeff = Self::calculate_coefficient(
-            Self::attack_time(self.color_type),
+            Self::attack_time(self.color_type) * timing,
             self.sample_rate
         );
         self.release_coeff = Self::calculate_coefficient(
-            Self::release_time(self.color_type),
+            Self::release_time(self.color_type) * timing,
             self.sample_rate
         );
     }
 
     pub fn process(&mut self, input: f32) -> f32 {
         // First apply input drive to increase level and introduce saturation
-        let driven = input * self.drive;
+        let driven = input * self.drive * self.variance_drive;
         
         // Apply saturation - soft clipping
         let saturated = self.saturate(driven);
@@ -107,7 +257,14 @@ impl Colorizer {
             ColorType::Warm => -12.0,
             ColorType::Bright => -24.0,
             ColorType::Vintage => -18.0,
-        };
+            ColorType::Tube => -15.0,
+            ColorType::Tape => -12.0,
+            ColorType::Transformer => -14.0,
+            ColorType::Diode => -20.0,
+            ColorType::Fet => -18.0,
+            ColorType::Exciter => -24.0,
+            ColorType::LoFi => -18.0,
+        } + self.tolerances.threshold * self.variance * VARIANCE_MAX_THRESHOLD_DB;
         
         // Calculate excess - how much signal is above threshold
         let excess_db = input_db - threshold;
@@ -124,7 +281,7 @@ impl Colorizer {
 
         // Apply frequency-specific processing based on color type and level
         let processed = match self.color_type {
-            ColorType::Warm => {
+            ColorType::Warm | ColorType::Tape | ColorType::Transformer => {
                 // For warm, boost low-mids when signal is strong
                 let boost = if smoothed_excess > 0.0 {
                     db_to_linear(smoothed_excess * 0.3 * self.intensity)
@@ -133,7 +290,7 @@ impl Colorizer {
                 };
                 saturated * boost
             },
-            ColorType::Bright => {
+            ColorType::Bright | ColorType::Fet => {
                 // For bright, add harmonic excitement on peaks
                 if smoothed_excess > 0.0 {
                     // Add subtle harmonics based on level
@@ -143,7 +300,7 @@ impl Colorizer {
                     saturated
                 }
             },
-            ColorType::Vintage => {
+            ColorType::Vintage | ColorType::Tube | ColorType::Diode => {
                 // For vintage, apply mild compression with saturation
                 if smoothed_excess > 0.0 {
                     let compression = 1.0 - (smoothed_excess * 0.02 * self.intensity);
@@ -155,43 +312,315 @@ impl Colorizer {
                     saturated
                 }
             }
+            // These are already controlled by their own parameters
+            ColorType::Exciter | ColorType::LoFi => saturated,
         };
-        
-        // Apply output level adjustment to keep overall volume consistent
-        processed * (1.0 / (0.5 + (self.intensity * 0.5)))
+
+        // Output level and level matching are handled by the device after the mix
+        self.dc_blocker.process(processed)
     }
     
-    // Simple saturation function
-    fn saturate(&self, input: f32) -> f32 {
+    /// The drive and the waveshaper on their own, without the emphasis filters and the level
+    /// dependent stage. Used by the editor to plot the transfer curve.
+    pub fn transfer(&mut self, input: f32) -> f32 {
+        let driven = input * self.drive * self.variance_drive;
+        match self.color_type {
+            ColorType::Exciter | ColorType::LoFi => self.saturate(driven),
+            _ if self.saturation <= 0.0 => driven,
+            _ => self.shape(driven),
+        }
+    }
+
+    // Saturation wrapped in the pre- and de-emphasis, so the tilt only changes which
+    // frequencies saturate first and not the linear tone
+    fn saturate(&mut self, input: f32) -> f32 {
+        // The exciter and the lo-fi degradation have their own controls and don't use the
+        // saturation amount. The tape machine always runs, its transport and latency don't
+        // depend on how hard the tape is hit.
+        match self.color_type {
+            ColorType::Exciter => return self.exciter.process(input),
+            ColorType::LoFi => return self.lofi.process(input),
+            ColorType::Tape => return self.tape.process(input, self.saturation),
+            _ => {}
+        }
+
         if self.saturation <= 0.0 {
             return input;
         }
-        
-        // Apply different saturation curves based on color type
+
+        let emphasized = self.emphasis.pre(input);
+        let shaped = self.shape(emphasized);
+        self.emphasis.de(shaped)
+    }
+
+    fn shape(&mut self, input: f32) -> f32 {
+        // The models with memory can't use ADAA and rely on oversampling instead
+        match self.color_type {
+            ColorType::Tape => return self.tape.process(input, self.saturation),
+            ColorType::Transformer => return self.transformer.process(input, self.saturation),
+            ColorType::Diode => return self.diode.process(input, self.saturation),
+            _ => {}
+        }
+
+        let curve = SaturationCurve {
+            color_type: self.color_type,
+            saturation: self.saturation as f64,
+        };
+        let input = input as f64;
+
+        let output = match self.adaa_order {
+            AdaaOrder::Off => curve.value(input),
+            AdaaOrder::FirstOrder => self.adaa.process_first_order(input, &curve),
+            AdaaOrder::SecondOrder => self.adaa.process_second_order(input, &curve),
+        };
+
+        output as f32
+    }
+    
+    // Reset internal state
+    pub fn reset(&mut self) {
+        self.envelope = 0.0;
+        self.prev_gain_db = 0.0;
+        self.prev_excess_db = 0.0;
+        self.adaa.reset();
+        self.emphasis.reset();
+        self.dc_blocker.reset();
+        self.tape.reset();
+        self.transformer.reset();
+        self.diode.reset();
+        self.exciter.reset();
+        self.lofi.reset();
+    }
+}
+
+/// The static saturation curve for a color type, along with its antiderivatives for ADAA.
+/// Where possible they are expressed through a normalized curve with the saturation amount
+/// factored out, so the closed forms stay well-conditioned as the saturation approaches zero.
+struct SaturationCurve {
+    color_type: ColorType,
+    saturation: f64,
+}
+
+impl Antiderivative for SaturationCurve {
+    fn value(&self, x: f64) -> f64 {
+        let s = self.saturation;
         match self.color_type {
             ColorType::Warm => {
                 // Soft tape-like saturation
-                input * (1.0 + self.saturation * input.abs()).recip()
+                x * (1.0 + s * x.abs()).recip()
             },
             ColorType::Bright => {
                 // Brighter saturation with harmonic enhancement
-                let base = input.tanh() * (1.0 - self.saturation) + input * self.saturation;
+                let base = x.tanh() * (1.0 - s) + x * s;
                 // Add subtle second harmonic
-                base + (input * input * 0.1 * self.saturation).tanh()
+                base + (x * x * 0.1 * s).tanh()
             },
             ColorType::Vintage => {
                 // More aggressive curve with a bit of asymmetry
-                let x = input * (1.0 + self.saturation);
-                (x / (1.0 + x.abs() + (x * x * 0.1))) * (1.0 + self.saturation * 0.2)
-            }
+                let y = x * (1.0 + s);
+                (y / (1.0 + y.abs() + (y * y * 0.1))) * (1.0 + s * 0.2)
+            },
+            ColorType::Tube => {
+                // A tanh stage biased off center, normalized to unity gain around zero. The
+                // bias makes the curve asymmetric, so even harmonics rise with the drive.
+                let b = tube_bias(s);
+                ((x + b).tanh() - b.tanh()) * b.cosh().powi(2)
+            },
+            ColorType::Fet => {
+                // Soft clipping plus a square-law term
+                let t = x.tanh();
+                t + s * t * t
+            },
+            // The models with memory are handled in `Colorizer::saturate()` and `Colorizer::shape()`
+            ColorType::Tape
+            | ColorType::Transformer
+            | ColorType::Diode
+            | ColorType::Exciter
+            | ColorType::LoFi => x,
         }
     }
-    
-    // Reset internal state
-    pub fn reset(&mut self) {
-        self.envelope = 0.0;
-        self.prev_gain_db = 0.0;
-        self.prev_excess_db = 0.0;
+
+    fn first(&self, x: f64) -> f64 {
+        let s = self.saturation;
+        match self.color_type {
+            // x / (1 + s|x|) = w(sx) / s with w(t) = t / (1 + |t|)
+            ColorType::Warm => warm_first(s * x) / (s * s),
+            ColorType::Bright => {
+                (1.0 - s) * tanh_table().first(x) + s * x * x / 2.0 + squared_tanh_first(0.1 * s, x)
+            },
+            ColorType::Vintage => {
+                let a = 1.0 + s;
+                let g = 1.0 + s * 0.2;
+                g / a * vintage_first(a * x)
+            },
+            ColorType::Tube => {
+                let b = tube_bias(s);
+                let table = tanh_table();
+                (table.first(x + b) - table.first(b) - x * b.tanh()) * b.cosh().powi(2)
+            },
+            // tanh^2 integrates to x - tanh(x)
+            ColorType::Fet => tanh_table().first(x) + s * (x - x.tanh()),
+            ColorType::Tape
+            | ColorType::Transformer
+            | ColorType::Diode
+            | ColorType::Exciter
+            | ColorType::LoFi => {
+                x * x / 2.0
+            },
+        }
+    }
+
+    fn second(&self, x: f64) -> f64 {
+        let s = self.saturation;
+        match self.color_type {
+            ColorType::Warm => warm_second(s * x) / (s * s * s),
+            ColorType::Bright => {
+                (1.0 - s) * tanh_table().second(x)
+                    + s * x * x * x / 6.0
+                    + squared_tanh_second(0.1 * s, x)
+            },
+            ColorType::Vintage => {
+                let a = 1.0 + s;
+                let g = 1.0 + s * 0.2;
+                g / (a * a) * vintage_second(a * x)
+            },
+            ColorType::Tube => {
+                let b = tube_bias(s);
+                let table = tanh_table();
+                (table.second(x + b) - table.second(b) - x * table.first(b) - x * x / 2.0 * b.tanh())
+                    * b.cosh().powi(2)
+            },
+            ColorType::Fet => {
+                let table = tanh_table();
+                table.second(x) + s * (x * x / 2.0 - table.first(x))
+            },
+            ColorType::Tape
+            | ColorType::Transformer
+            | ColorType::Diode
+            | ColorType::Exciter
+            | ColorType::LoFi => {
+                x * x * x / 6.0
+            },
+        }
+    }
+}
+
+// The triode's operating point moves further off center as it's driven harder
+fn tube_bias(saturation: f64) -> f64 {
+    0.1 + saturation
+}
+
+// (1 + z) ln(1 + z) - z, the integral of ln(1 + z) from zero
+fn log_integral(z: f64) -> f64 {
+    (1.0 + z) * z.ln_1p() - z
+}
+
+// Antiderivatives of t / (1 + |t|), using the Taylor series near zero where the closed forms
+// cancel out
+fn warm_first(t: f64) -> f64 {
+    let u = t.abs();
+    if u < 1.0e-2 {
+        u * u * (0.5 - u / 3.0 + u * u / 4.0 - u * u * u / 5.0)
+    } else {
+        u - u.ln_1p()
+    }
+}
+
+fn warm_second(t: f64) -> f64 {
+    let u = t.abs();
+    let value = if u < 1.0e-2 {
+        u * u * u * (1.0 / 6.0 - u / 12.0 + u * u / 20.0 - u * u * u / 30.0)
+    } else {
+        u * u / 2.0 - log_integral(u)
+    };
+    value.copysign(t)
+}
+
+// Antiderivatives of tanh(k x^2), scaled from the tabulated tanh(t^2) with t = sqrt(k) x. The
+// Taylor series covers the small arguments where dividing by k would amplify table errors.
+fn squared_tanh_first(k: f64, x: f64) -> f64 {
+    let root_k = k.sqrt();
+    let t = root_k * x;
+    if t.abs() < 0.5 {
+        let t4 = t * t * t * t;
+        t * t * t * (1.0 / 3.0 - t4 / 21.0 + 2.0 * t4 * t4 / 165.0) / root_k
+    } else {
+        squared_tanh_table().first(t) / root_k
+    }
+}
+
+fn squared_tanh_second(k: f64, x: f64) -> f64 {
+    let t = k.sqrt() * x;
+    if t.abs() < 0.5 {
+        let t4 = t * t * t * t;
+        t4 * (1.0 / 12.0 - t4 / 168.0 + t4 * t4 / 990.0) / k
+    } else {
+        squared_tanh_table().second(t) / k
+    }
+}
+
+// y / (1 + |y| + 0.1 y^2) splits into partial fractions over the roots of 0.1 y^2 + y + 1,
+// which gives closed forms in terms of logarithms
+fn vintage_poles() -> (f64, f64, f64, f64) {
+    let root = 0.6_f64.sqrt();
+    let p1 = 5.0 * (1.0 - root);
+    let p2 = 5.0 * (1.0 + root);
+    (p1, p2, -p1 / (p2 - p1), p2 / (p2 - p1))
+}
+
+fn vintage_first(y: f64) -> f64 {
+    let u = y.abs();
+    if u < 1.0e-3 {
+        return u * u * (0.5 - u / 3.0 + 0.9 * u * u / 4.0);
+    }
+
+    let (p1, p2, a, b) = vintage_poles();
+    10.0 * (a * (u / p1).ln_1p() + b * (u / p2).ln_1p())
+}
+
+fn vintage_second(y: f64) -> f64 {
+    let u = y.abs();
+    let value = if u < 1.0e-3 {
+        u * u * u * (1.0 / 6.0 - u / 12.0 + 0.9 * u * u / 20.0)
+    } else {
+        let (p1, p2, a, b) = vintage_poles();
+        10.0 * (a * p1 * log_integral(u / p1) + b * p2 * log_integral(u / p2))
+    };
+    value.copysign(y)
+}
+
+// Largest deviations at full analog variance
+const VARIANCE_MAX_DRIVE_DB: f32 = 1.0;
+const VARIANCE_MAX_THRESHOLD_DB: f32 = 1.5;
+const VARIANCE_MAX_TIMING: f32 = 0.15;
+
+/// Per channel component tolerances, each between -1.0 and 1.0. Real consoles never have
+/// two identical channels, so the analog variance control scales these fixed deviations.
+#[derive(Copy, Clone)]
+struct ComponentTolerances {
+    drive: f32,
+    threshold: f32,
+    timing: f32,
+}
+
+impl ComponentTolerances {
+    fn from_seed(seed: u64) -> Self {
+        // SplitMix64, good enough to decorrelate neighbouring seeds
+        let mut state = seed;
+        let mut next = || {
+            state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+            let mut z = state;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            z ^= z >> 31;
+            (z >> 40) as f32 / (1u64 << 24) as f32 * 2.0 - 1.0
+        };
+
+        Self {
+            drive: next(),
+            threshold: next(),
+            timing: next(),
+        }
     }
 }
 
@@ -205,4 +634,47 @@ fn linear_to_db(linear: f32) -> f32 {
 
 fn db_to_linear(db: f32) -> f32 {
     10.0_f32.powf(db / 20.0)
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::PI;
+
+    const SAMPLE_RATE: f32 = 48000.0;
+    // 100 Hz fits a whole number of periods into a second
+    const FREQUENCY: f32 = 100.0;
+
+    // A fundamental with a phase shifted second harmonic, so the peaks on either side differ
+    fn asymmetric_signal(n: usize) -> f32 {
+        let phase = 2.0 * PI * FREQUENCY * n as f32 / SAMPLE_RATE;
+        0.5 * phase.sin() + 0.25 * (2.0 * phase + PI / 2.0).sin()
+    }
+
+    #[test]
+    fn no_dc_offset_at_full_intensity() {
+        let settle_samples = 2 * SAMPLE_RATE as usize;
+        let measure_samples = SAMPLE_RATE as usize;
+
+        for index in 0..ColorType::variants().len() {
+            let color_type = ColorType::from_index(index);
+            let mut colorizer = Colorizer::new(SAMPLE_RATE);
+            colorizer.set_color_type(color_type);
+            colorizer.set_intensity(1.0);
+            colorizer.set_saturation(1.0);
+
+            for n in 0..settle_samples {
+                colorizer.process(asymmetric_signal(n));
+            }
+            let mean = (settle_samples..settle_samples + measure_samples)
+                .map(|n| colorizer.process(asymmetric_signal(n)) as f64)
+                .sum::<f64>()
+                / measure_samples as f64;
+
+            assert!(
+                mean.abs() < 1e-3,
+                "{} leaves a DC offset of {mean}",
+                ColorType::variants()[index]
+            );
+        }
+    }
+}
@@ -1,11 +1,45 @@
 use nih_plug::buffer::Buffer;
 use nih_plug::prelude::*;
+use nih_plug::wrapper::state::ParamValue;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::adaa::AdaaOrder;
 use crate::colorizer::{Colorizer, ColorType};
-use crate::device::Device;
+use crate::delay::DelayLine;
+use crate::device::{Device, DEFAULT_NUM_CHANNELS, MAX_SAMPLE_RATE};
+use crate::gain::AutoGain;
+use crate::lofi::LoFiFilter;
+use crate::oversampler::{Oversampler, OversamplingFactor, OversamplingPhase};
+use crate::seed_registry::SeedClaim;
+use crate::tape::{TapeSpeed, WOW_FLUTTER_DELAY};
+
+// A seat on the console strays at least this far from nominal, so the seats differ even with
+// the Analog Variance knob at zero
+const CONSOLE_MIN_VARIANCE: f32 = 0.3;
 
 
 pub struct ColorizerDevice {
-    colorizer: Colorizer,
+    // One colorizer per channel, the envelope and anti-aliasing state must not be shared
+    colorizers: Vec<Colorizer>,
+    // The saturation runs inside a per-channel oversampler to keep aliasing down
+    oversamplers: Vec<Oversampler>,
+    // The tape mode's wow and flutter delay in samples at the host rate
+    tape_latency: u32,
+    // Keeps the dry signal aligned with the colorizer's latency for the mix
+    dry_delays: Vec<DelayLine>,
+    auto_gains: Vec<AutoGain>,
+    auto_gain: bool,
+    mix: f32,
+    output_gain: f32,
+    sample_rate: f32,
+    // Seed the colorizers' component tolerances were last derived from
+    variance_seed: u64,
+    // Keeps other instances in this process from using the same stored seed
+    seed_claim: Option<SeedClaim>,
+    // Replaces the instance's own seed while it sits on the console
+    seed_override: Option<u64>,
     // colorizer_params: Arc<ColorizerDeviceParams>,
 }
 
@@ -15,6 +49,57 @@ pub struct ColorizerDeviceParams {
     pub intensity: FloatParam,
     #[id = "color_type"]
     pub color_type: EnumParam<ColorType>,
+    #[id = "color_tone"]
+    pub tone: FloatParam,
+    #[id = "color_drive"]
+    pub drive: FloatParam,
+    #[id = "color_saturation"]
+    pub saturation: FloatParam,
+    #[id = "color_output"]
+    pub output: FloatParam,
+    #[id = "color_mix"]
+    pub mix: FloatParam,
+    #[id = "color_auto_gain"]
+    pub auto_gain: BoolParam,
+    #[id = "color_analog_variance"]
+    pub analog_variance: FloatParam,
+    /// Seed for the analog variance tolerances. Every new instance gets its own, and it's
+    /// stored with the session so a recalled instance sounds the same. A duplicated instance
+    /// starts out with a copy of the seed and rolls a new one when it finds it taken.
+    #[persist = "color_variance_seed"]
+    pub variance_seed: AtomicU64,
+    #[id = "color_oversampling"]
+    pub oversampling: EnumParam<OversamplingFactor>,
+    #[id = "color_oversampling_phase"]
+    pub oversampling_phase: EnumParam<OversamplingPhase>,
+    #[id = "color_antialiasing"]
+    pub antialiasing: EnumParam<AdaaOrder>,
+    #[id = "color_exciter_frequency"]
+    pub exciter_frequency: FloatParam,
+    #[id = "color_exciter_harmonics"]
+    pub exciter_harmonics: FloatParam,
+    #[id = "color_exciter_amount"]
+    pub exciter_amount: FloatParam,
+    #[id = "color_lofi_bits"]
+    pub lofi_bits: FloatParam,
+    #[id = "color_lofi_dither"]
+    pub lofi_dither: BoolParam,
+    #[id = "color_lofi_rate"]
+    pub lofi_rate: FloatParam,
+    #[id = "color_lofi_filter"]
+    pub lofi_filter: EnumParam<LoFiFilter>,
+    #[id = "color_lofi_crackle"]
+    pub lofi_crackle: FloatParam,
+    #[id = "color_tape_speed"]
+    pub tape_speed: EnumParam<TapeSpeed>,
+    #[id = "color_tape_wow"]
+    pub tape_wow: FloatParam,
+    #[id = "color_tape_flutter"]
+    pub tape_flutter: FloatParam,
+    #[id = "color_tape_hiss"]
+    pub tape_hiss: BoolParam,
+    #[id = "color_tape_hiss_level"]
+    pub tape_hiss_level: FloatParam,
 }
 
 impl ColorizerDeviceParams {
@@ -34,15 +119,282 @@ impl ColorizerDeviceParams {
             .with_string_to_value(formatters::s2v_f32_percentage()),
             
             color_type: EnumParam::new("Colorizer:Type", ColorType::Warm),
+
+            tone: FloatParam::new(
+                "Colorizer:Tone",
+                0.0,
+                FloatRange::Linear {
+                    min: -1.0,
+                    max: 1.0,
+                },
+            )
+            .with_value_to_string(formatters::v2s_f32_rounded(2)),
+            drive: FloatParam::new(
+                "Colorizer:Drive",
+                util::db_to_gain(0.0),
+                FloatRange::Skewed {
+                    min: util::db_to_gain(0.0),
+                    max: util::db_to_gain(24.0),
+                    factor: FloatRange::gain_skew_factor(0.0, 24.0),
+                },
+            )
+            .with_unit(" dB")
+            .with_value_to_string(formatters::v2s_f32_gain_to_db(1))
+            .with_string_to_value(formatters::s2v_f32_gain_to_db()),
+            saturation: FloatParam::new(
+                "Colorizer:Saturation",
+                0.0,
+                FloatRange::Linear {
+                    min: 0.0,
+                    max: 1.0,
+                },
+            )
+            .with_unit("%")
+            .with_value_to_string(formatters::v2s_f32_percentage(0))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
+            output: FloatParam::new(
+                "Colorizer:Output",
+                util::db_to_gain(0.0),
+                FloatRange::Skewed {
+                    min: util::db_to_gain(-24.0),
+                    max: util::db_to_gain(24.0),
+                    factor: FloatRange::gain_skew_factor(-24.0, 24.0),
+                },
+            )
+            .with_unit(" dB")
+            .with_value_to_string(formatters::v2s_f32_gain_to_db(1))
+            .with_string_to_value(formatters::s2v_f32_gain_to_db()),
+            mix: FloatParam::new(
+                "Colorizer:Mix",
+                1.0,
+                FloatRange::Linear {
+                    min: 0.0,
+                    max: 1.0,
+                },
+            )
+            .with_unit("%")
+            .with_value_to_string(formatters::v2s_f32_percentage(0))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
+            auto_gain: BoolParam::new("Colorizer:Auto Gain", false),
+            analog_variance: FloatParam::new(
+                "Colorizer:Analog Variance",
+                0.0,
+                FloatRange::Linear {
+                    min: 0.0,
+                    max: 1.0,
+                },
+            )
+            .with_unit("%")
+            .with_value_to_string(formatters::v2s_f32_percentage(0))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
+            variance_seed: AtomicU64::new(
+                SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|time| time.as_nanos() as u64)
+                    .unwrap_or(0),
+            ),
+
+            oversampling: EnumParam::new("Colorizer:Quality", OversamplingFactor::Times2),
+            oversampling_phase: EnumParam::new("Colorizer:Quality:Phase", OversamplingPhase::Minimum),
+            antialiasing: EnumParam::new("Colorizer:Antialiasing", AdaaOrder::Off),
+
+            exciter_frequency: FloatParam::new(
+                "Colorizer:Exciter:Freq",
+                6000.0,
+                FloatRange::Skewed {
+                    min: 1000.0,
+                    max: 16000.0,
+                    factor: FloatRange::skew_factor(-1.0),
+                },
+            )
+            .with_unit(" Hz")
+            .with_value_to_string(formatters::v2s_f32_hz_then_khz(1))
+            .with_string_to_value(formatters::s2v_f32_hz_then_khz()),
+            exciter_harmonics: FloatParam::new(
+                "Colorizer:Exciter:Harmonics",
+                0.3,
+                FloatRange::Linear {
+                    min: 0.0,
+                    max: 1.0,
+                },
+            )
+            .with_value_to_string(Arc::new(|value| {
+                format!("{:.0}% even / {:.0}% odd", (1.0 - value) * 100.0, value * 100.0)
+            }))
+            .with_string_to_value(Arc::new(|string| {
+                // Takes the displayed form, either half of it or a bare odd percentage
+                let string = string.to_lowercase();
+                let percentage = |part: &str| {
+                    part.trim_end_matches(|c: char| c.is_alphabetic() || c.is_whitespace())
+                        .trim_end_matches('%')
+                        .trim()
+                        .parse::<f32>()
+                        .ok()
+                        .map(|percentage| percentage / 100.0)
+                };
+
+                let mut parts = string.split('/').map(str::trim);
+                if let Some(odd) = parts.clone().find(|part| part.contains("odd")) {
+                    percentage(odd)
+                } else if let Some(even) = parts.find(|part| part.contains("even")) {
+                    percentage(even).map(|even| 1.0 - even)
+                } else {
+                    percentage(string.trim())
+                }
+            })),
+            exciter_amount: FloatParam::new(
+                "Colorizer:Exciter:Amount",
+                0.5,
+                FloatRange::Linear {
+                    min: 0.0,
+                    max: 1.0,
+                },
+            )
+            .with_unit("%")
+            .with_value_to_string(formatters::v2s_f32_percentage(0))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
+
+            lofi_bits: FloatParam::new(
+                "Colorizer:LoFi:Bits",
+                8.0,
+                FloatRange::Linear {
+                    min: 2.0,
+                    max: 16.0,
+                },
+            )
+            .with_unit(" bits")
+            .with_value_to_string(formatters::v2s_f32_rounded(1)),
+            lofi_dither: BoolParam::new("Colorizer:LoFi:Dither", false),
+            lofi_rate: FloatParam::new(
+                "Colorizer:LoFi:Rate",
+                11025.0,
+                FloatRange::Skewed {
+                    min: 1000.0,
+                    max: 48000.0,
+                    factor: FloatRange::skew_factor(-1.5),
+                },
+            )
+            .with_unit(" Hz")
+            .with_value_to_string(formatters::v2s_f32_hz_then_khz(1))
+            .with_string_to_value(formatters::s2v_f32_hz_then_khz()),
+            lofi_filter: EnumParam::new("Colorizer:LoFi:Filter", LoFiFilter::Telephone),
+            lofi_crackle: FloatParam::new(
+                "Colorizer:LoFi:Crackle",
+                0.0,
+                FloatRange::Linear {
+                    min: 0.0,
+                    max: 1.0,
+                },
+            )
+            .with_unit("%")
+            .with_value_to_string(formatters::v2s_f32_percentage(0))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
+
+            tape_speed: EnumParam::new("Colorizer:Tape:Speed", TapeSpeed::Medium),
+            tape_wow: FloatParam::new(
+                "Colorizer:Tape:Wow",
+                0.2,
+                FloatRange::Linear {
+                    min: 0.0,
+                    max: 1.0,
+                },
+            )
+            .with_unit("%")
+            .with_value_to_string(formatters::v2s_f32_percentage(0))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
+            tape_flutter: FloatParam::new(
+                "Colorizer:Tape:Flutter",
+                0.2,
+                FloatRange::Linear {
+                    min: 0.0,
+                    max: 1.0,
+                },
+            )
+            .with_unit("%")
+            .with_value_to_string(formatters::v2s_f32_percentage(0))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
+            tape_hiss: BoolParam::new("Colorizer:Tape:Hiss", false),
+            tape_hiss_level: FloatParam::new(
+                "Colorizer:Tape:Hiss:Level",
+                -70.0,
+                FloatRange::Linear {
+                    min: -90.0,
+                    max: -40.0,
+                },
+            )
+            .with_unit(" dB")
+            .with_value_to_string(formatters::v2s_f32_rounded(1)),
+        }
+    }
+}
+
+impl ColorizerDeviceParams {
+    /// Sessions from before the intensity was split up only store the intensity, which used to
+    /// set the drive, saturation and output level as well. Recreates those from it so these
+    /// sessions keep sounding the same.
+    pub fn migrate_intensity(params: &mut BTreeMap<String, ParamValue>, id_prefix: &str) {
+        let id = |id: &str| format!("{id_prefix}_{id}");
+        if params.contains_key(&id("color_saturation")) {
+            return;
         }
+        let Some(&ParamValue::F32(intensity)) = params.get(&id("color_intensity")) else {
+            return;
+        };
+
+        let intensity = intensity.clamp(0.0, 1.0);
+        params.insert(id("color_drive"), ParamValue::F32(1.0 + intensity * 3.0));
+        params.insert(id("color_saturation"), ParamValue::F32(intensity));
+        params.insert(id("color_output"), ParamValue::F32(1.0 / (0.5 + intensity * 0.5)));
     }
 }
 
 impl ColorizerDevice {
     pub fn new() -> Self {
-        Self {
-            colorizer: Colorizer::new(44100.0),
+        let mut device = Self {
+            colorizers: (0..DEFAULT_NUM_CHANNELS)
+                .map(|_| Colorizer::new(44100.0))
+                .collect(),
+            oversamplers: (0..DEFAULT_NUM_CHANNELS)
+                .map(|_| Oversampler::new(OversamplingFactor::Times2, OversamplingPhase::Minimum))
+                .collect(),
+            tape_latency: 0,
+            dry_delays: (0..DEFAULT_NUM_CHANNELS)
+                .map(|_| DelayLine::new(Self::max_latency_samples()))
+                .collect(),
+            auto_gains: (0..DEFAULT_NUM_CHANNELS)
+                .map(|_| AutoGain::new(44100.0))
+                .collect(),
+            auto_gain: false,
+            mix: 1.0,
+            output_gain: 1.0,
+            sample_rate: 44100.0,
+            variance_seed: 0,
+            seed_claim: None,
+            seed_override: None,
             // colorizer_params: Arc::new(ColorizerDeviceParams::new()),
+        };
+        device.seed_colorizers();
+        device
+    }
+
+    // The longest the dry signal ever needs to be delayed, with the slowest oversampling and
+    // the tape machine at the highest supported rate
+    fn max_latency_samples() -> usize {
+        Oversampler::max_latency_samples() as usize
+            + (WOW_FLUTTER_DELAY * MAX_SAMPLE_RATE).round() as usize
+    }
+
+    /// Uses `seed` for the analog variance instead of the instance's own seed, or goes back
+    /// to that seed for `None`. While overridden the variance never drops below the console's
+    /// own tolerances.
+    pub fn set_seed_override(&mut self, seed: Option<u64>) {
+        self.seed_override = seed;
+    }
+
+    // Every channel gets its own tolerances, derived from the instance's seed
+    fn seed_colorizers(&mut self) {
+        for (idx, colorizer) in self.colorizers.iter_mut().enumerate() {
+            colorizer.set_seed(self.variance_seed.wrapping_add(idx as u64));
         }
     }
 }
@@ -59,26 +411,163 @@ impl Default for ColorizerDevice {
 //     fn reset_state(&mut self);
 // }
 
+/// Sets everything on `colorizer` that follows directly from the parameters, with the analog
+/// variance at least `min_variance`. Shared with the editor, which runs its own colorizer to
+/// draw the curve and measure the harmonics.
+pub fn apply_params(colorizer: &mut Colorizer, params: &ColorizerDeviceParams, min_variance: f32) {
+    colorizer.set_intensity(params.intensity.value());
+    colorizer.set_drive(params.drive.value());
+    colorizer.set_saturation(params.saturation.value());
+    colorizer.set_tone(params.tone.value());
+    colorizer.set_variance(params.analog_variance.value().max(min_variance));
+    colorizer.set_color_type(params.color_type.value());
+    colorizer.set_adaa_order(params.antialiasing.value());
+
+    colorizer.exciter.set_frequency(params.exciter_frequency.value());
+    colorizer.exciter.set_harmonics(params.exciter_harmonics.value());
+    colorizer.exciter.set_amount(params.exciter_amount.value());
+
+    colorizer.lofi.set_bit_depth(params.lofi_bits.value(), params.lofi_dither.value());
+    colorizer.lofi.set_target_rate(params.lofi_rate.value());
+    colorizer.lofi.set_filter(params.lofi_filter.value());
+    colorizer.lofi.set_crackle(params.lofi_crackle.value());
+
+    colorizer.tape.set_speed(params.tape_speed.value());
+    colorizer.tape.set_wow_flutter(params.tape_wow.value(), params.tape_flutter.value());
+    colorizer.tape.set_hiss(params.tape_hiss.value(), params.tape_hiss_level.value());
+}
+
 impl Device for ColorizerDevice {
 
     type Params = ColorizerDeviceParams;
 
     fn update(&mut self, sample_rate: f32, params: &ColorizerDeviceParams) {
-        self.colorizer.sample_rate = sample_rate;
-        self.colorizer.set_intensity(params.intensity.value());
-        self.colorizer.set_color_type(params.color_type.value());
+        let factor = params.oversampling.value();
+        let phase = params.oversampling_phase.value();
+        for oversampler in self.oversamplers.iter_mut() {
+            oversampler.set_mode(factor, phase);
+        }
+
+        // Keep the tape delay at a whole number of host samples so the latency is exact. Past
+        // the highest supported rate it stays at the length the buffers were sized for.
+        let tape_delay = (WOW_FLUTTER_DELAY * sample_rate.min(MAX_SAMPLE_RATE)).round();
+        self.tape_latency = tape_delay as u32;
+
+        let min_variance = if self.seed_override.is_some() {
+            CONSOLE_MIN_VARIANCE
+        } else {
+            0.0
+        };
+        for colorizer in self.colorizers.iter_mut() {
+            // The colorizer's time constants need to match the rate it actually runs at
+            colorizer.set_sample_rate(sample_rate * factor.factor() as f32);
+            colorizer.tape.set_delay(tape_delay * factor.factor() as f32);
+            apply_params(colorizer, params, min_variance);
+        }
+
+        // Restoring a session changes the stored seed, so claim whatever it is now
+        let stored_seed = params.variance_seed.load(Ordering::Relaxed);
+        if self.seed_claim.as_ref().map(SeedClaim::seed) != Some(stored_seed) {
+            // Release the old seed first so the instance can't collide with itself
+            self.seed_claim = None;
+            let claim = SeedClaim::claim(stored_seed);
+            params.variance_seed.store(claim.seed(), Ordering::Relaxed);
+            self.seed_claim = Some(claim);
+        }
+
+        let variance_seed = self
+            .seed_override
+            .unwrap_or_else(|| params.variance_seed.load(Ordering::Relaxed));
+        if self.variance_seed != variance_seed {
+            self.variance_seed = variance_seed;
+            self.seed_colorizers();
+        }
+
+        let latency = self.latency_samples() as usize;
+        for dry_delay in self.dry_delays.iter_mut() {
+            dry_delay.set_delay(latency);
+        }
+
+        if self.sample_rate != sample_rate {
+            self.sample_rate = sample_rate;
+            for auto_gain in self.auto_gains.iter_mut() {
+                auto_gain.set_sample_rate(sample_rate);
+            }
+        }
+
+        let auto_gain = params.auto_gain.value();
+        if self.auto_gain != auto_gain {
+            self.auto_gain = auto_gain;
+            self.auto_gains.iter_mut().for_each(|auto_gain| auto_gain.reset());
+        }
+        self.mix = params.mix.value();
+        self.output_gain = params.output.value();
     }
 
     fn run(&mut self, input: &mut Buffer) {
         for mut sample_channels in input.iter_samples() {
-            for sample in sample_channels.iter_mut() {
-                *sample = self.colorizer.process(*sample);
+            for (idx, sample) in sample_channels.iter_mut().enumerate() {
+                let dry = self.dry_delays[idx].process(*sample);
+                let colorizer = &mut self.colorizers[idx];
+                let mut wet = self.oversamplers[idx].process(*sample, |x| colorizer.process(x));
+
+                if self.auto_gain {
+                    wet = self.auto_gains[idx].process(dry, wet);
+                }
+
+                *sample = (dry + (wet - dry) * self.mix) * self.output_gain;
             }
         }
     }
 
     fn reset_state(&mut self) {
-        self.colorizer.reset();
+        self.colorizers.iter_mut().for_each(|colorizer| colorizer.reset());
+        self.oversamplers.iter_mut().for_each(|oversampler| oversampler.reset());
+        self.dry_delays.iter_mut().for_each(|dry_delay| dry_delay.reset());
+        self.auto_gains.iter_mut().for_each(|auto_gain| auto_gain.reset());
+    }
+
+    fn set_num_channels(&mut self, num_channels: usize) {
+        let sample_rate = self
+            .colorizers
+            .first()
+            .map(|colorizer| colorizer.sample_rate)
+            .unwrap_or(44100.0);
+        self.colorizers
+            .resize_with(num_channels, || Colorizer::new(sample_rate));
+        self.seed_colorizers();
+
+        let (factor, phase) = self
+            .oversamplers
+            .first()
+            .map(|oversampler| (oversampler.factor(), oversampler.phase()))
+            .unwrap_or((OversamplingFactor::Times2, OversamplingPhase::Minimum));
+        self.oversamplers
+            .resize_with(num_channels, || Oversampler::new(factor, phase));
+
+        let latency = self.latency_samples() as usize;
+        self.dry_delays.resize_with(num_channels, || {
+            let mut dry_delay = DelayLine::new(Self::max_latency_samples());
+            dry_delay.set_delay(latency);
+            dry_delay
+        });
+        let sample_rate = self.sample_rate;
+        self.auto_gains
+            .resize_with(num_channels, || AutoGain::new(sample_rate));
+    }
+
+    fn latency_samples(&self) -> u32 {
+        let oversampling_latency = self
+            .oversamplers
+            .first()
+            .map(|oversampler| oversampler.latency_samples())
+            .unwrap_or(0);
+        let tape_latency = match self.colorizers.first() {
+            Some(colorizer) if colorizer.color_type == ColorType::Tape => self.tape_latency,
+            _ => 0,
+        };
+
+        oversampling_latency + tape_latency
     }
 }
 
@@ -1,5 +1,11 @@
 use nih_plug::prelude::Enum;
 
+use crate::device::MAX_SAMPLE_RATE;
+
+// Width of the soft knee around the threshold
+pub const KNEE_WIDTH_DB: f32 = 6.0;
+const LOOKAHEAD_TIME: f32 = 0.005;
+
 pub struct Compressor {
     pub threshold: f32,  // dB
     pub ratio: Ratio,
@@ -13,7 +19,9 @@ pub struct Compressor {
     release_coeff: f32,
     gain_attack_coeff: f32,
     gain_release_coeff: f32,
+    // Sized for the highest supported rate, only the first `lookahead_len` samples are used
     lookahead_buffer: Vec<f32>,
+    lookahead_len: usize,
     lookahead_pos: usize,
 }
 
@@ -39,7 +47,7 @@ pub enum Ratio {
 impl Compressor {
     pub fn new(sample_rate: f32) -> Self {
         let preset = CompressionPreset::Drums;
-        let lookahead_samples = (0.005 * sample_rate).round() as usize; // 5ms lookahead
+        let max_lookahead_samples = (LOOKAHEAD_TIME * MAX_SAMPLE_RATE).round() as usize;
         
         // Calculate coefficients
         let attack_coeff = Self::calculate_coefficient(
@@ -64,7 +72,7 @@ impl Compressor {
             ratio: Ratio::Half,
             preset,
             sample_rate,
-            knee_width: 6.0, // Default 6dB knee width
+            knee_width: KNEE_WIDTH_DB,
             envelope: 0.0,
             prev_gain_db: 0.0,
             prev_excess_db: 0.0,
@@ -72,11 +80,31 @@ impl Compressor {
             release_coeff,
             gain_attack_coeff,
             gain_release_coeff,
-            lookahead_buffer: vec![0.0; lookahead_samples],
+            lookahead_buffer: vec![0.0; max_lookahead_samples],
+            lookahead_len: Self::lookahead_samples(sample_rate, max_lookahead_samples),
             lookahead_pos: 0,
         }
     }
 
+    // 5ms lookahead, limited to what the buffer can hold
+    fn lookahead_samples(sample_rate: f32, max_lookahead_samples: usize) -> usize {
+        ((LOOKAHEAD_TIME * sample_rate).round() as usize).min(max_lookahead_samples)
+    }
+
+    /// Changes the sample rate without reallocating the lookahead buffer, so this is safe to
+    /// call from the audio thread.
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+        self.update_coefficients();
+        self.lookahead_len = Self::lookahead_samples(sample_rate, self.lookahead_buffer.len());
+        self.reset();
+    }
+
+    /// The lookahead delays the audio by its full length.
+    pub fn latency_samples(&self) -> u32 {
+        self.lookahead_len as u32
+    }
+
     fn calculate_coefficient(time_in_seconds: f32, sample_rate: f32) -> f32 {
         (-1.0 / (time_in_seconds * sample_rate)).exp() // More accurate coefficient calculation
     }
@@ -169,7 +197,7 @@ impl Compressor {
     }
 
     // Soft knee calculation
-    fn calculate_knee(&self, excess_db: f32, knee_width: f32) -> f32 {
+    fn calculate_knee(excess_db: f32, knee_width: f32) -> f32 {
         if excess_db <= -knee_width / 2.0 {
             0.0
         } else if excess_db >= knee_width / 2.0 {
@@ -180,13 +208,20 @@ impl Compressor {
         }
     }
 
+    /// The static gain change in dB for a steady detector level, i.e. the transfer curve
+    /// without the attack and release smoothing.
+    pub fn static_gain_db(level_db: f32, threshold: f32, ratio: Ratio, knee_width: f32) -> f32 {
+        let knee_excess = Self::calculate_knee(level_db - threshold, knee_width);
+        -knee_excess * (1.0 - 1.0 / Self::ratio_to_value(ratio))
+    }
+
     pub fn process(&mut self, input: f32) -> f32 {
         // Store input in lookahead buffer
         let delayed_sample;
-        if !self.lookahead_buffer.is_empty() {
+        if self.lookahead_len > 0 {
             delayed_sample = self.lookahead_buffer[self.lookahead_pos];
             self.lookahead_buffer[self.lookahead_pos] = input;
-            self.lookahead_pos = (self.lookahead_pos + 1) % self.lookahead_buffer.len();
+            self.lookahead_pos = (self.lookahead_pos + 1) % self.lookahead_len;
         } else {
             delayed_sample = input;
         }
@@ -203,7 +238,7 @@ impl Compressor {
         let rms_db = linear_to_db(rms_linear);
         
         let excess_db = rms_db - self.threshold;
-        let knee_excess = self.calculate_knee(excess_db, self.knee_width);
+        let knee_excess = Self::calculate_knee(excess_db, self.knee_width);
         
         let smoothed_excess = if knee_excess > self.prev_excess_db {
             self.prev_excess_db + self.attack_coeff * (knee_excess - self.prev_excess_db)
@@ -232,10 +267,20 @@ impl Compressor {
         delayed_sample * gain
     }
     
+    /// The level the detector currently sees, in dB.
+    pub fn detector_db(&self) -> f32 {
+        linear_to_db(self.envelope.sqrt())
+    }
+
+    /// The gain reduction applied to the last sample, as a positive number of dB.
+    pub fn gain_reduction_db(&self) -> f32 {
+        -self.prev_gain_db
+    }
+
     // Reset the compressor state
     pub fn reset(&mut self) {
         self.lookahead_pos = 0;
-        self.lookahead_buffer.clear();
+        self.lookahead_buffer.iter_mut().for_each(|sample| *sample = 0.0);
         self.envelope = 0.0;
         self.prev_gain_db = 0.0;
         self.prev_excess_db = 0.0;
@@ -1,7 +1,9 @@
 use nih_plug::buffer::Buffer;
 use nih_plug::prelude::*;
 use crate::compressor::{Compressor, Ratio, CompressionPreset};
-use crate::device::Device;
+use crate::device::{Device, DEFAULT_NUM_CHANNELS};
+use crate::meters::{GainReductionMeter, GainReductionRecorder};
+use std::sync::Arc;
 
 impl Default for CompressorDevice {
     fn default() -> Self {
@@ -34,15 +36,31 @@ impl CompressorDeviceParams {
 }
 
 pub struct CompressorDevice {
-    compressor: Compressor,
+    // One compressor per channel so the detectors and lookahead buffers don't interleave
+    compressors: Vec<Compressor>,
+    // Publishes the gain reduction to the editor
+    recorder: Option<GainReductionRecorder>,
 }
 
 impl CompressorDevice {
     fn new() -> Self {
         Self {
-            compressor: Compressor::new(44100.0),
+            compressors: (0..DEFAULT_NUM_CHANNELS)
+                .map(|_| Compressor::new(44100.0))
+                .collect(),
+            recorder: None,
         }
     }
+
+    /// Starts publishing the gain reduction to `meter`.
+    pub fn set_meter(&mut self, meter: Arc<GainReductionMeter>) {
+        let sample_rate = self
+            .compressors
+            .first()
+            .map(|compressor| compressor.sample_rate)
+            .unwrap_or(44100.0);
+        self.recorder = Some(GainReductionRecorder::new(meter, sample_rate));
+    }
 }
 
 impl Device for CompressorDevice {
@@ -58,27 +76,64 @@ impl Device for CompressorDevice {
 
         // updated is called at the start of the process loop so should only update when needed. 
         // compare value with current value and only update if different
-        if self.compressor.sample_rate == sample_rate  {
-            self.compressor.sample_rate = sample_rate;
-            self.reset_state();
+        for compressor in self.compressors.iter_mut() {
+            if compressor.sample_rate != sample_rate {
+                compressor.set_sample_rate(sample_rate);
+                if let Some(recorder) = self.recorder.as_mut() {
+                    recorder.set_sample_rate(sample_rate);
+                }
+            }
 
+            compressor.threshold = _compressor_params.threshold.value();
+            compressor.ratio = _compressor_params.ratio.value();
+            compressor.set_preset(_compressor_params.preset.value());
         }
-
-        self.compressor.threshold = _compressor_params.threshold.value();
-        self.compressor.ratio = _compressor_params.ratio.value();
-        self.compressor.set_preset(_compressor_params.preset.value());
-        
     }
 
     fn run(&mut self, input: &mut Buffer) {
         for mut sample_channels in input.iter_samples() {
-            for (_, sample) in sample_channels.iter_mut().enumerate() {
-                *sample = self.compressor.process(*sample);
+            for (idx, sample) in sample_channels.iter_mut().enumerate() {
+                let compressor = &mut self.compressors[idx];
+                *sample = compressor.process(*sample);
+
+                if let Some(recorder) = self.recorder.as_mut() {
+                    recorder.record(
+                        idx,
+                        *sample,
+                        compressor.detector_db(),
+                        compressor.gain_reduction_db(),
+                    );
+                }
+            }
+
+            if let Some(recorder) = self.recorder.as_mut() {
+                recorder.advance();
             }
         }
+
+        if let Some(recorder) = self.recorder.as_mut() {
+            recorder.finish_block(self.compressors.len());
+        }
     }
 
     fn reset_state(&mut self) {
-        self.compressor.reset();
+        self.compressors.iter_mut().for_each(|compressor| compressor.reset());
+    }
+
+    fn set_num_channels(&mut self, num_channels: usize) {
+        let sample_rate = self
+            .compressors
+            .first()
+            .map(|compressor| compressor.sample_rate)
+            .unwrap_or(44100.0);
+        self.compressors
+            .resize_with(num_channels, || Compressor::new(sample_rate));
+    }
+
+    fn latency_samples(&self) -> u32 {
+        self.compressors
+            .first()
+            .map(|compressor| compressor.latency_samples())
+            .unwrap_or(0)
     }
 }
\ No newline at end of file
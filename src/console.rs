@@ -0,0 +1,121 @@
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
+
+// Number of KVP Channel instances that can share one console
+pub const CONSOLE_MAX_CHANNELS: usize = 64;
+// Samples of history each channel publishes, must be a power of two
+pub const CONSOLE_RING_SIZE: usize = 4096;
+
+/// One console channel as seen by the other instances. Samples and levels are stored as
+/// `f32` bits so everything stays lock-free.
+struct ConsoleSlot {
+    in_use: AtomicBool,
+    write_pos: AtomicUsize,
+    mean_square: AtomicU32,
+    ring: [AtomicU32; CONSOLE_RING_SIZE],
+}
+
+#[allow(clippy::declare_interior_mutable_const)]
+const EMPTY_SAMPLE: AtomicU32 = AtomicU32::new(0);
+#[allow(clippy::declare_interior_mutable_const)]
+const EMPTY_SLOT: ConsoleSlot = ConsoleSlot {
+    in_use: AtomicBool::new(false),
+    write_pos: AtomicUsize::new(0),
+    mean_square: AtomicU32::new(0),
+    ring: [EMPTY_SAMPLE; CONSOLE_RING_SIZE],
+};
+
+// Shared by every instance loaded in the same process
+static REGISTRY: [ConsoleSlot; CONSOLE_MAX_CHANNELS] = [EMPTY_SLOT; CONSOLE_MAX_CHANNELS];
+
+/// An instance's place on the shared console. The slot is released again when this is
+/// dropped.
+pub struct ConsoleChannel {
+    index: usize,
+    write_pos: usize,
+}
+
+impl ConsoleChannel {
+    /// Claims the first free slot, or returns `None` when the console is full.
+    pub fn register() -> Option<Self> {
+        let index = REGISTRY.iter().position(|slot| {
+            slot.in_use
+                .compare_exchange(false, true, Ordering::AcqRel, Ordering::Relaxed)
+                .is_ok()
+        })?;
+
+        // Don't let the next instance hear whatever the previous owner left behind
+        let slot = &REGISTRY[index];
+        slot.ring
+            .iter()
+            .for_each(|sample| sample.store(0, Ordering::Relaxed));
+        slot.mean_square.store(0.0f32.to_bits(), Ordering::Relaxed);
+        slot.write_pos.store(0, Ordering::Release);
+
+        Some(Self {
+            index,
+            write_pos: 0,
+        })
+    }
+
+    /// The slot this channel occupies, which doubles as its position on the desk.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Publishes one sample of this channel's mono signal.
+    pub fn write(&mut self, sample: f32) {
+        REGISTRY[self.index].ring[self.write_pos].store(sample.to_bits(), Ordering::Relaxed);
+        self.write_pos = (self.write_pos + 1) & (CONSOLE_RING_SIZE - 1);
+    }
+
+    /// Makes the samples written so far visible to the other channels, along with the
+    /// block's mean square level.
+    pub fn publish(&self, mean_square: f32) {
+        let slot = &REGISTRY[self.index];
+        slot.mean_square.store(mean_square.to_bits(), Ordering::Relaxed);
+        slot.write_pos.store(self.write_pos, Ordering::Release);
+    }
+
+    /// Collects the other channels into `others` as `(slot, read position)` pairs, where the
+    /// read position points `num_samples` back from the last published sample. Returns the
+    /// number of channels found and the summed mean square level of the whole console.
+    ///
+    /// Instances run independently of each other so this is never sample accurate, but at
+    /// crosstalk levels a block of misalignment doesn't matter.
+    pub fn others(
+        &self,
+        num_samples: usize,
+        others: &mut [(usize, usize); CONSOLE_MAX_CHANNELS],
+    ) -> (usize, f32) {
+        let mut count = 0;
+        let mut bus_mean_square = 0.0;
+        for (index, slot) in REGISTRY.iter().enumerate() {
+            if !slot.in_use.load(Ordering::Acquire) {
+                continue;
+            }
+
+            bus_mean_square += f32::from_bits(slot.mean_square.load(Ordering::Relaxed));
+            if index != self.index {
+                let write_pos = slot.write_pos.load(Ordering::Acquire);
+                let read_pos = write_pos.wrapping_sub(num_samples) & (CONSOLE_RING_SIZE - 1);
+                others[count] = (index, read_pos);
+                count += 1;
+            }
+        }
+
+        (count, bus_mean_square)
+    }
+
+    /// Reads one sample from another channel's history.
+    pub fn read(index: usize, pos: usize) -> f32 {
+        f32::from_bits(REGISTRY[index].ring[pos & (CONSOLE_RING_SIZE - 1)].load(Ordering::Relaxed))
+    }
+}
+
+impl Drop for ConsoleChannel {
+    fn drop(&mut self) {
+        let slot = &REGISTRY[self.index];
+        slot.mean_square.store(0.0f32.to_bits(), Ordering::Relaxed);
+        slot.in_use.store(false, Ordering::Release);
+    }
+}
@@ -0,0 +1,188 @@
+use nih_plug::buffer::{Block, Buffer};
+use nih_plug::prelude::*;
+use std::f32::consts::PI;
+use crate::console::{ConsoleChannel, CONSOLE_MAX_CHANNELS, CONSOLE_RING_SIZE};
+use crate::device::{Device, DEFAULT_NUM_CHANNELS};
+
+// Capacitive crosstalk between neighbouring channels rises with frequency
+const CROSSTALK_HIGHPASS_HZ: f32 = 1000.0;
+// How much of the summing curve a desk at 0 dBFS RMS brings in at full summing
+const CONSOLE_SUMMING_AMOUNT: f32 = 0.5;
+// Spacing between the variance seeds of neighbouring desk positions, leaves room for
+// every channel of a multichannel instance
+const CONSOLE_SEED_STRIDE: u64 = 16;
+const CONSOLE_SEED_BASE: u64 = 0x4b56_5043_4f4e_534f;
+
+#[derive(Params)]
+pub struct ConsoleDeviceParams {
+    #[id = "console_enabled"]
+    pub enabled: BoolParam,
+    #[id = "console_crosstalk"]
+    pub crosstalk: FloatParam,
+    #[id = "console_summing"]
+    pub summing: FloatParam,
+}
+
+impl ConsoleDeviceParams {
+    pub fn new() -> Self {
+        Self {
+            enabled: BoolParam::new("Console:Enabled", false),
+            crosstalk: FloatParam::new(
+                "Console:Crosstalk",
+                -70.0,
+                FloatRange::Linear {
+                    min: -90.0,
+                    max: -50.0,
+                },
+            )
+            .with_unit(" dB")
+            .with_value_to_string(formatters::v2s_f32_rounded(1)),
+            summing: FloatParam::new(
+                "Console:Summing",
+                0.5,
+                FloatRange::Linear {
+                    min: 0.0,
+                    max: 1.0,
+                },
+            )
+            .with_unit("%")
+            .with_value_to_string(formatters::v2s_f32_percentage(0))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
+        }
+    }
+}
+
+/// Console mode. Every instance with it enabled takes a seat on a shared desk, hears a
+/// trace of the other seats as crosstalk and saturates slightly more as the whole desk
+/// gets louder.
+pub struct ConsoleDevice {
+    channel: Option<ConsoleChannel>,
+    num_channels: usize,
+    crosstalk_gain: f32,
+    summing: f32,
+    highpass_coeff: f32,
+    highpass_x1: f32,
+    highpass_y1: f32,
+    // (slot, read position) of the other seats for the current block
+    others: [(usize, usize); CONSOLE_MAX_CHANNELS],
+}
+
+impl ConsoleDevice {
+    pub fn new() -> Self {
+        let mut device = Self {
+            channel: None,
+            num_channels: DEFAULT_NUM_CHANNELS,
+            crosstalk_gain: 0.0,
+            summing: 0.0,
+            highpass_coeff: 0.0,
+            highpass_x1: 0.0,
+            highpass_y1: 0.0,
+            others: [(0, 0); CONSOLE_MAX_CHANNELS],
+        };
+        device.set_sample_rate(44100.0);
+        device
+    }
+
+    fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.highpass_coeff = (-2.0 * PI * CROSSTALK_HIGHPASS_HZ / sample_rate).exp();
+    }
+
+    /// The variance seed for this instance's desk position, if it has one. The colorizer
+    /// uses this instead of its own seed so every seat has its own tolerances.
+    pub fn variant_seed(&self) -> Option<u64> {
+        self.channel.as_ref().map(|channel| {
+            CONSOLE_SEED_BASE.wrapping_add(channel.index() as u64 * CONSOLE_SEED_STRIDE)
+        })
+    }
+
+    fn highpass(&mut self, input: f32) -> f32 {
+        let output = self.highpass_coeff * (self.highpass_y1 + input - self.highpass_x1);
+        self.highpass_x1 = input;
+        self.highpass_y1 = output;
+        output
+    }
+
+    fn run_block(&mut self, block: &mut Block) {
+        let Some(channel) = self.channel.as_ref() else {
+            return;
+        };
+
+        let (num_others, bus_mean_square) = channel.others(block.samples(), &mut self.others);
+        let nonlinearity =
+            (self.summing * bus_mean_square.sqrt() * CONSOLE_SUMMING_AMOUNT).min(1.0);
+        let scale = 1.0 / self.num_channels.max(1) as f32;
+
+        let mut mean_square = 0.0;
+        for (sample_idx, mut sample_channels) in block.iter_samples().enumerate() {
+            // Publish this seat's signal before anything from the others is added, so
+            // nothing feeds back around the desk
+            let mono = sample_channels.iter_mut().map(|sample| *sample).sum::<f32>() * scale;
+            mean_square += mono * mono;
+            if let Some(channel) = self.channel.as_mut() {
+                channel.write(mono);
+            }
+
+            let bleed = self.others[..num_others]
+                .iter()
+                .map(|&(index, pos)| ConsoleChannel::read(index, pos + sample_idx))
+                .sum::<f32>();
+            let crosstalk = self.highpass(bleed) * self.crosstalk_gain;
+
+            for sample in sample_channels.iter_mut() {
+                // Clean on a quiet desk, bending more towards the curve as the bus gets louder
+                *sample += (sample.tanh() - *sample) * nonlinearity + crosstalk;
+            }
+        }
+
+        if let Some(channel) = self.channel.as_ref() {
+            channel.publish(mean_square / block.samples().max(1) as f32);
+        }
+    }
+}
+
+impl Default for ConsoleDevice {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Device for ConsoleDevice {
+
+    type Params = ConsoleDeviceParams;
+
+    fn update(&mut self, sample_rate: f32, params: &ConsoleDeviceParams) {
+        if params.enabled.value() {
+            if self.channel.is_none() {
+                self.channel = ConsoleChannel::register();
+            }
+        } else {
+            // Leaving the desk frees the seat for other instances
+            self.channel = None;
+        }
+
+        self.set_sample_rate(sample_rate);
+        self.crosstalk_gain = util::db_to_gain(params.crosstalk.value());
+        self.summing = params.summing.value();
+    }
+
+    fn run(&mut self, input: &mut Buffer) {
+        if self.channel.is_none() {
+            return;
+        }
+
+        // The other seats can only be read up to half a ring back, so long blocks are run in
+        // chunks that each fetch the others again
+        for (_, mut block) in input.iter_blocks(CONSOLE_RING_SIZE / 2) {
+            self.run_block(&mut block);
+        }
+    }
+
+    fn reset_state(&mut self) {
+        self.highpass_x1 = 0.0;
+        self.highpass_y1 = 0.0;
+    }
+
+    fn set_num_channels(&mut self, num_channels: usize) {
+        self.num_channels = num_channels;
+    }
+}
@@ -0,0 +1,40 @@
+use std::f32::consts::PI;
+
+// Low enough to leave the audible low end alone
+const DC_BLOCKER_CUTOFF_HZ: f32 = 5.0;
+
+/// One pole, one zero DC blocking highpass. Asymmetric curves and even harmonics leave an
+/// offset behind that would otherwise eat headroom further down the session.
+pub struct DcBlocker {
+    pole: f32,
+    x1: f32,
+    y1: f32,
+}
+
+impl DcBlocker {
+    pub fn new(sample_rate: f32) -> Self {
+        let mut dc_blocker = Self {
+            pole: 0.0,
+            x1: 0.0,
+            y1: 0.0,
+        };
+        dc_blocker.set_sample_rate(sample_rate);
+        dc_blocker
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.pole = (-2.0 * PI * DC_BLOCKER_CUTOFF_HZ / sample_rate).exp();
+    }
+
+    pub fn process(&mut self, input: f32) -> f32 {
+        let output = input - self.x1 + self.pole * self.y1;
+        self.x1 = input;
+        self.y1 = output;
+        output
+    }
+
+    pub fn reset(&mut self) {
+        self.x1 = 0.0;
+        self.y1 = 0.0;
+    }
+}
@@ -0,0 +1,38 @@
+/// A fixed delay of a whole number of samples, used to keep a dry signal aligned with a
+/// processed signal that has latency. The buffer is sized for the longest delay up front, so
+/// the delay can change on the audio thread.
+pub struct DelayLine {
+    buffer: Vec<f32>,
+    delay: usize,
+    pos: usize,
+}
+
+impl DelayLine {
+    /// Creates a delay line that can delay by up to `max_delay_samples`, starting without
+    /// any delay.
+    pub fn new(max_delay_samples: usize) -> Self {
+        Self {
+            buffer: vec![0.0; max_delay_samples + 1],
+            delay: 0,
+            pos: 0,
+        }
+    }
+
+    /// Changes the delay without reallocating, delays past the maximum are clamped.
+    pub fn set_delay(&mut self, delay_samples: usize) {
+        self.delay = delay_samples.min(self.buffer.len() - 1);
+    }
+
+    pub fn process(&mut self, input: f32) -> f32 {
+        let len = self.buffer.len();
+        self.buffer[self.pos] = input;
+        let output = self.buffer[(self.pos + len - self.delay) % len];
+        self.pos = (self.pos + 1) % len;
+        output
+    }
+
+    pub fn reset(&mut self) {
+        self.buffer.iter_mut().for_each(|sample| *sample = 0.0);
+        self.pos = 0;
+    }
+}
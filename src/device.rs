@@ -1,23 +1,43 @@
 use nih_plug::buffer::Buffer;
 use nih_plug::prelude::*;
+use nih_plug::wrapper::state::PluginState;
 use nih_plug_vizia::ViziaState;
 use std::{
     num::NonZero,
     sync::Arc,
 };
 
+use crate::ab::{AbState, SWITCH_FADE_TIME};
+use crate::analyzer::AnalyzerParams;
 use crate::colorizor_device::{ColorizerDevice,ColorizerDeviceParams};
+use crate::dc_blocker::DcBlocker;
 use crate::compressor_device::{CompressorDevice, CompressorDeviceParams};
+use crate::console_device::{ConsoleDevice, ConsoleDeviceParams};
 use crate::eq_device::{EqDevice, EqDeviceParams};
+use crate::loudness::{LoudnessAnalyzer, LoudnessTarget};
+use crate::meters::Meters;
+use crate::theme::{EditorTheme, ThemeState};
 use crate::ui::{self, create_editor};
 
 
 
+// Channel count used until the host negotiates a layout in `initialize()`
+pub const DEFAULT_NUM_CHANNELS: usize = 2;
+// Highest host sample rate delay buffers are sized for up front, so changing the rate never
+// allocates on the audio thread
+pub const MAX_SAMPLE_RATE: f32 = 192000.0;
+
 pub trait Device {
     type Params: Params;
     fn run(&mut self, input: &mut Buffer);
     fn update(&mut self, sample_rate: f32, params: &Self::Params);
     fn reset_state(&mut self);
+    /// Resizes any per-channel state to match the channel count negotiated with the host.
+    fn set_num_channels(&mut self, num_channels: usize);
+    /// The delay this device adds to the signal, in samples.
+    fn latency_samples(&self) -> u32 {
+        0
+    }
 }
 
 // Create a Plugin Implementation of the various devices put together in a chain. 
@@ -27,34 +47,117 @@ pub struct KVPChannelPlugin {
     pub eq: EqDevice,
     pub compressor: CompressorDevice,
     pub colorizer: ColorizerDevice,
+    pub console: ConsoleDevice,
     pub params: Arc<KVPChannelPluginParams>,
-    pub editor_state: Arc<ViziaState>
+    // Levels published to the editor
+    pub meters: Arc<Meters>,
+    num_input_channels: usize,
+    num_output_channels: usize,
+    // Last latency reported to the host
+    reported_latency: u32,
+    // Optional DC filter on the plugin output, one per channel
+    output_dc_blockers: Vec<DcBlocker>,
+    // Loudness of the plugin output, published through `meters.loudness`
+    loudness: LoudnessAnalyzer,
+    // A/B loudness match gain applied at the end of the last block
+    match_gain: f32,
+    // Output gain of the fade around A/B switches
+    switch_gain: f32,
+    sample_rate: f32,
 }
 
 impl KVPChannelPlugin {
     pub fn update(&mut self, sample_rate: f32) {
         self.eq.update(sample_rate, &self.params.eq_params);
         self.compressor.update(sample_rate, &self.params.compressor_params);
+        self.console.update(sample_rate, &self.params.console_params);
+        // On the console every seat gets the tolerances of its own desk position
+        self.colorizer.set_seed_override(self.console.variant_seed());
         self.colorizer.update(sample_rate, &self.params.colorizer_params);
+
+        if self.sample_rate != sample_rate {
+            self.sample_rate = sample_rate;
+            for dc_blocker in self.output_dc_blockers.iter_mut() {
+                dc_blocker.set_sample_rate(sample_rate);
+            }
+            self.loudness.set_sample_rate(sample_rate);
+            self.meters.set_sample_rate(sample_rate);
+        }
+    }
+
+    pub fn set_num_channels(&mut self, num_channels: usize) {
+        self.eq.set_num_channels(num_channels);
+        self.compressor.set_num_channels(num_channels);
+        self.colorizer.set_num_channels(num_channels);
+        self.console.set_num_channels(num_channels);
+
+        let sample_rate = self.sample_rate;
+        self.output_dc_blockers
+            .resize_with(num_channels, || DcBlocker::new(sample_rate));
+        self.loudness.set_num_channels(num_channels);
+    }
+
+    pub fn latency_samples(&self) -> u32 {
+        self.eq.latency_samples()
+            + self.compressor.latency_samples()
+            + self.colorizer.latency_samples()
+            + self.console.latency_samples()
+    }
+
+    /// For layouts with fewer inputs than outputs (mono to stereo) the host leaves the extra
+    /// output channels empty, so fill them with the input before processing.
+    fn upmix(&self, buffer: &mut Buffer) {
+        if self.num_input_channels == 0 || self.num_input_channels >= self.num_output_channels {
+            return;
+        }
+
+        let channels = buffer.as_slice();
+        let (inputs, extra) = channels.split_at_mut(self.num_input_channels);
+        for (idx, channel) in extra.iter_mut().enumerate() {
+            channel.copy_from_slice(&inputs[idx % self.num_input_channels][..]);
+        }
     }
 }
 
 #[derive(Params)]
 pub struct KVPChannelPluginParams {
+    // Window size and UI scale, restored with the session
+    #[persist = "editor-state"]
+    pub editor_state: Arc<ViziaState>,
+    #[persist = "editor-theme"]
+    pub editor_theme: Arc<ThemeState>,
+    // The A/B slot that isn't loaded into the parameters
+    #[persist = "ab-slots"]
+    pub ab_state: Arc<AbState>,
     #[nested(id_prefix = "eq")]
     pub eq_params: Arc<EqDeviceParams>,
     #[nested(id_prefix = "compressor")]
     pub compressor_params: Arc<CompressorDeviceParams>,
     #[nested(id_prefix = "colorizer")]
     pub colorizer_params: Arc<ColorizerDeviceParams>,
+    #[nested(id_prefix = "console")]
+    pub console_params: Arc<ConsoleDeviceParams>,
+    #[nested(id_prefix = "analyzer")]
+    pub analyzer_params: Arc<AnalyzerParams>,
+    #[id = "output_dc_block"]
+    pub output_dc_block: BoolParam,
+    #[id = "loudness_target"]
+    pub loudness_target: EnumParam<LoudnessTarget>,
 }
 
 impl Default for KVPChannelPluginParams {
     fn default() -> Self {
         Self {
+            editor_state: ViziaState::new(|| (800, 540)),
+            editor_theme: ThemeState::new(EditorTheme::Rust),
+            ab_state: AbState::new(),
             eq_params: Arc::new(EqDeviceParams::new()),
             compressor_params: Arc::new(CompressorDeviceParams::new()),
             colorizer_params: Arc::new(ColorizerDeviceParams::new()),
+            console_params: Arc::new(ConsoleDeviceParams::new()),
+            analyzer_params: Arc::new(AnalyzerParams::new()),
+            output_dc_block: BoolParam::new("Output:DC Filter", false),
+            loudness_target: EnumParam::new("Output:Loudness Target", LoudnessTarget::Streaming),
         }
     }
 }
@@ -64,12 +167,27 @@ impl Default for KVPChannelPlugin {
     /// equalization, compression, and colorization devices using default parameters.
 
     fn default() -> Self {
+        let meters = Arc::new(Meters::default());
+        let mut compressor = CompressorDevice::default();
+        compressor.set_meter(meters.gain_reduction.clone());
+
         Self {
             eq: EqDevice::new(44100.0),
-            compressor: CompressorDevice::default(),
+            compressor,
             colorizer: ColorizerDevice::default(),
+            console: ConsoleDevice::default(),
             params: Arc::new(KVPChannelPluginParams::default()),
-            editor_state: ViziaState::new(|| (800, 540))
+            meters,
+            num_input_channels: DEFAULT_NUM_CHANNELS,
+            num_output_channels: DEFAULT_NUM_CHANNELS,
+            reported_latency: 0,
+            output_dc_blockers: (0..DEFAULT_NUM_CHANNELS)
+                .map(|_| DcBlocker::new(44100.0))
+                .collect(),
+            loudness: LoudnessAnalyzer::new(44100.0, DEFAULT_NUM_CHANNELS),
+            match_gain: 1.0,
+            switch_gain: 1.0,
+            sample_rate: 44100.0,
         }
     }
 }
@@ -85,11 +203,45 @@ impl Plugin for KVPChannelPlugin {
 
     const VERSION: &'static str = "1.0.0";
 
-    const AUDIO_IO_LAYOUTS: &'static [AudioIOLayout] = &[AudioIOLayout {
-        main_input_channels: NonZero::new(2),
-        main_output_channels: NonZero::new(2),
-        ..AudioIOLayout::const_default()
-    }];
+    // The first layout is the default, the rest are offered to hosts that support them
+    const AUDIO_IO_LAYOUTS: &'static [AudioIOLayout] = &[
+        // Stereo
+        AudioIOLayout {
+            main_input_channels: NonZero::new(2),
+            main_output_channels: NonZero::new(2),
+            ..AudioIOLayout::const_default()
+        },
+        // Mono
+        AudioIOLayout {
+            main_input_channels: NonZero::new(1),
+            main_output_channels: NonZero::new(1),
+            ..AudioIOLayout::const_default()
+        },
+        // Mono to stereo
+        AudioIOLayout {
+            main_input_channels: NonZero::new(1),
+            main_output_channels: NonZero::new(2),
+            ..AudioIOLayout::const_default()
+        },
+        // Quad
+        AudioIOLayout {
+            main_input_channels: NonZero::new(4),
+            main_output_channels: NonZero::new(4),
+            ..AudioIOLayout::const_default()
+        },
+        // 5.1
+        AudioIOLayout {
+            main_input_channels: NonZero::new(6),
+            main_output_channels: NonZero::new(6),
+            ..AudioIOLayout::const_default()
+        },
+        // 7.1
+        AudioIOLayout {
+            main_input_channels: NonZero::new(8),
+            main_output_channels: NonZero::new(8),
+            ..AudioIOLayout::const_default()
+        },
+    ];
 
     type SysExMessage = ();
 
@@ -99,10 +251,43 @@ impl Plugin for KVPChannelPlugin {
         self.params.clone()
     }
 
+    fn filter_state(state: &mut PluginState) {
+        ColorizerDeviceParams::migrate_intensity(&mut state.params, "colorizer");
+    }
+
+    fn initialize(
+        &mut self,
+        audio_io_layout: &AudioIOLayout,
+        buffer_config: &BufferConfig,
+        context: &mut impl InitContext<Self>,
+    ) -> bool {
+        self.num_input_channels = audio_io_layout
+            .main_input_channels
+            .map(NonZero::get)
+            .unwrap_or(0) as usize;
+        self.num_output_channels = audio_io_layout
+            .main_output_channels
+            .map(NonZero::get)
+            .unwrap_or(0) as usize;
+
+        // Every device keeps one set of filters/envelopes per output channel
+        self.set_num_channels(self.num_output_channels);
+        self.update(buffer_config.sample_rate);
+
+        self.reported_latency = self.latency_samples();
+        context.set_latency_samples(self.reported_latency);
+
+        true
+    }
+
     fn reset(&mut self) {
         self.eq.reset_state();
         self.compressor.reset_state();
         self.colorizer.reset_state();
+        self.console.reset_state();
+        self.output_dc_blockers.iter_mut().for_each(|dc_blocker| dc_blocker.reset());
+        self.loudness.reset();
+        self.meters.loudness.clear();
     }
 
     fn process(
@@ -112,13 +297,90 @@ impl Plugin for KVPChannelPlugin {
         context: &mut impl ProcessContext<Self>,
     ) -> ProcessStatus {
         self.update(context.transport().sample_rate);
+
+        // Switching the oversampling mode changes the latency
+        let latency_samples = self.latency_samples();
+        if latency_samples != self.reported_latency {
+            self.reported_latency = latency_samples;
+            context.set_latency_samples(latency_samples);
+        }
+
+        // Metering is only needed while someone is looking at it
+        let metering = self.params.editor_state.is_open();
+        let sample_rate = self.sample_rate;
+
+        self.upmix(buffer);
+        if metering {
+            self.meters.input.measure(buffer, sample_rate);
+            self.meters.pre_eq_spectrum.write(buffer);
+        }
         self.eq.run(buffer);
+        if metering {
+            self.meters.post_eq.measure(buffer, sample_rate);
+            self.meters.post_eq_spectrum.write(buffer);
+        }
         self.compressor.run(buffer);
+        if metering {
+            self.meters.post_compressor.measure(buffer, sample_rate);
+        }
         self.colorizer.run(buffer);
+        self.console.run(buffer);
+
+        if self.params.output_dc_block.value() {
+            for mut sample_channels in buffer.iter_samples() {
+                for (idx, sample) in sample_channels.iter_mut().enumerate() {
+                    *sample = self.output_dc_blockers[idx].process(*sample);
+                }
+            }
+        }
+
+        // Ramped over the block so switching A/B slots doesn't click
+        let match_gain = util::db_to_gain(self.params.ab_state.match_gain_db());
+        if match_gain != 1.0 || self.match_gain != 1.0 {
+            let step = (match_gain - self.match_gain) / buffer.samples() as f32;
+            for (idx, mut sample_channels) in buffer.iter_samples().enumerate() {
+                let gain = self.match_gain + step * (idx + 1) as f32;
+                for sample in sample_channels.iter_mut() {
+                    *sample *= gain;
+                }
+            }
+            self.match_gain = match_gain;
+        }
+
+        // The editor only swaps the A/B parameters while this holds the output muted
+        self.params.ab_state.block_processed();
+        let fade_out = self.params.ab_state.switch_fade_out();
+        let target = if fade_out { 0.0 } else { 1.0 };
+        if fade_out || self.switch_gain != 1.0 {
+            let step = 1.0 / (SWITCH_FADE_TIME * sample_rate);
+            for mut sample_channels in buffer.iter_samples() {
+                self.switch_gain = if fade_out {
+                    (self.switch_gain - step).max(0.0)
+                } else {
+                    (self.switch_gain + step).min(1.0)
+                };
+                for sample in sample_channels.iter_mut() {
+                    *sample *= self.switch_gain;
+                }
+            }
+        }
+        if self.switch_gain == target {
+            self.params.ab_state.finish_switch_fade(fade_out);
+        }
+
+        if metering {
+            self.meters.output.measure(buffer, sample_rate);
+        }
+        // Loudness keeps integrating with the editor closed so the readings stay valid
+        self.loudness.process(buffer, &self.meters.loudness);
         ProcessStatus::Normal
     }
 
     fn editor(&mut self, async_executor: AsyncExecutor<Self>) -> Option<Box<dyn Editor>> {
-        create_editor(Arc::clone(&self.params), Arc::clone(&self.editor_state))
+        create_editor(
+            Arc::clone(&self.params),
+            Arc::clone(&self.meters),
+            Arc::clone(&self.params.editor_state),
+        )
     }
 }
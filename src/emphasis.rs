@@ -0,0 +1,81 @@
+use biquad::{self, Biquad, ToHertz};
+
+// The tilt pivots around the low mids so "dark" favours body and "bright" favours air
+const EMPHASIS_PIVOT_HZ: f32 = 1000.0;
+// How far the Color Tone control tilts at either end
+pub const EMPHASIS_MAX_TILT_DB: f32 = 6.0;
+
+/// Pre- and de-emphasis around a nonlinearity. The pre-emphasis tilts the spectrum into the
+/// saturation and the de-emphasis undoes that tilt afterwards, so the saturation becomes
+/// frequency dependent while the linear response stays flat.
+///
+/// Both halves are a high shelf at the pivot with half the tilt as a make-up gain. RBJ
+/// shelves with opposite gains are exact inverses of each other.
+pub struct Emphasis {
+    sample_rate: f32,
+    tilt_db: f32,
+    pre: biquad::DirectForm1<f32>,
+    de: biquad::DirectForm1<f32>,
+    pre_gain: f32,
+    de_gain: f32,
+}
+
+impl Emphasis {
+    pub fn new(sample_rate: f32) -> Self {
+        Self {
+            sample_rate,
+            tilt_db: 0.0,
+            pre: biquad::DirectForm1::new(Self::shelf_coeffs(0.0, sample_rate)),
+            de: biquad::DirectForm1::new(Self::shelf_coeffs(0.0, sample_rate)),
+            pre_gain: 1.0,
+            de_gain: 1.0,
+        }
+    }
+
+    fn shelf_coeffs(gain_db: f32, sample_rate: f32) -> biquad::Coefficients<f32> {
+        biquad::Coefficients::<f32>::from_params(
+            biquad::Type::HighShelf(gain_db),
+            sample_rate.hz(),
+            EMPHASIS_PIVOT_HZ.hz(),
+            biquad::Q_BUTTERWORTH_F32,
+        )
+        .unwrap()
+    }
+
+    fn update_coefficients(&mut self) {
+        self.pre
+            .update_coefficients(Self::shelf_coeffs(self.tilt_db, self.sample_rate));
+        self.de
+            .update_coefficients(Self::shelf_coeffs(-self.tilt_db, self.sample_rate));
+        self.pre_gain = 10.0_f32.powf(-self.tilt_db / 40.0);
+        self.de_gain = 1.0 / self.pre_gain;
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        if self.sample_rate != sample_rate {
+            self.sample_rate = sample_rate;
+            self.update_coefficients();
+        }
+    }
+
+    /// Sets the tilt in dB, positive values push the highs into the saturation.
+    pub fn set_tilt(&mut self, tilt_db: f32) {
+        if self.tilt_db != tilt_db {
+            self.tilt_db = tilt_db;
+            self.update_coefficients();
+        }
+    }
+
+    pub fn pre(&mut self, input: f32) -> f32 {
+        self.pre.run(input * self.pre_gain)
+    }
+
+    pub fn de(&mut self, input: f32) -> f32 {
+        self.de.run(input) * self.de_gain
+    }
+
+    pub fn reset(&mut self) {
+        self.pre.reset_state();
+        self.de.reset_state();
+    }
+}
@@ -42,7 +42,7 @@ impl FilterSlope {
 impl InputEq {
     pub fn new(sample_rate: f32) -> Self {
         let fs = sample_rate.hz();
-        let num_filters = FilterSlope::Slope12DB.num_filters();
+        let num_filters = Self::num_stages();
         // let slope = FilterSlope::Slope12DB;
         
         // Initialize cascaded filters for highpass
@@ -78,28 +78,49 @@ impl InputEq {
         }
     }
 
+    /// The number of cascaded biquads in each of the input filters.
+    pub fn num_stages() -> usize {
+        FilterSlope::Slope12DB.num_filters()
+    }
+
+    /// The coefficients of a single highpass stage, shared with the editor's response display.
+    pub fn highpass_coefficients(
+        cutoff: f32,
+        sample_rate: f32,
+        mode: FilterSlope,
+    ) -> biquad::Coefficients<f32> {
+        biquad::Coefficients::<f32>::from_params(
+            biquad::Type::HighPass,
+            sample_rate.hz(),
+            cutoff.hz(),
+            mode.to_q()
+        ).unwrap()
+    }
+
+    /// The coefficients of a single lowpass stage, shared with the editor's response display.
+    pub fn lowpass_coefficients(
+        cutoff: f32,
+        sample_rate: f32,
+        mode: FilterSlope,
+    ) -> biquad::Coefficients<f32> {
+        biquad::Coefficients::<f32>::from_params(
+            biquad::Type::LowPass,
+            sample_rate.hz(),
+            cutoff.hz(),
+            mode.to_q()
+        ).unwrap()
+    }
+
     pub fn update_highpass(&mut self, cutoff: f32, sample_rate: f32, mode: FilterSlope) {
-        let fs = sample_rate.hz();
+        let coeffs = Self::highpass_coefficients(cutoff, sample_rate, mode);
         for filter in &mut self.highpass_filters {
-            let coeffs = biquad::Coefficients::<f32>::from_params(
-                biquad::Type::HighPass,
-                fs,
-                cutoff.hz(),
-                mode.to_q()
-            ).unwrap();
             filter.update_coefficients(coeffs);
         }
     }
 
     pub fn update_lowpass(&mut self, cutoff: f32, sample_rate: f32, mode: FilterSlope) {
-        let fs = sample_rate.hz();
+        let coeffs = Self::lowpass_coefficients(cutoff, sample_rate, mode);
         for filter in &mut self.lowpass_filters {
-            let coeffs = biquad::Coefficients::<f32>::from_params(
-                biquad::Type::LowPass,
-                fs,
-                cutoff.hz(),
-                mode.to_q()
-            ).unwrap();
             filter.update_coefficients(coeffs);
         }
     }
@@ -167,50 +188,77 @@ impl PullEq {
         }
     }
 
-    /// Updates the coefficients of the low shelf filter with the given cutoff frequency and gain.
-    /// This will change the sound of the low shelf filter.
-    pub fn update_lowshelf(&mut self, cutoff: f32, gain: f32, sample_rate: f32) {
-        let fs = sample_rate.hz();
-        let coeffs = biquad::Coefficients::<f32>::from_params(
+    /// The coefficients of the low shelf filter, shared with the editor's response display.
+    pub fn lowshelf_coefficients(
+        cutoff: f32,
+        gain: f32,
+        q: f32,
+        sample_rate: f32,
+    ) -> biquad::Coefficients<f32> {
+        biquad::Coefficients::<f32>::from_params(
             biquad::Type::LowShelf(gain),
-            fs,
+            sample_rate.hz(),
             cutoff.hz(),
-            biquad::Q_BUTTERWORTH_F32,
+            q,
         )
-        .unwrap();
+        .unwrap()
+    }
+
+    /// Updates the coefficients of the low shelf filter with the given cutoff frequency, gain and Q.
+    /// This will change the sound of the low shelf filter.
+    pub fn update_lowshelf(&mut self, cutoff: f32, gain: f32, q: f32, sample_rate: f32) {
+        let coeffs = Self::lowshelf_coefficients(cutoff, gain, q, sample_rate);
         self.lowshelf.update_coefficients(coeffs);
     }
 
-    /// Updates the coefficients of the low pull filter with the specified cutoff frequency and gain.
+    /// The coefficients of the low pull filter, shared with the editor's response display.
+    pub fn lowpull_coefficients(
+        cutoff: f32,
+        gain: f32,
+        q: f32,
+        sample_rate: f32,
+    ) -> biquad::Coefficients<f32> {
+        biquad::Coefficients::<f32>::from_params(
+            biquad::Type::PeakingEQ(gain),
+            sample_rate.hz(),
+            cutoff.hz(),
+            q,
+        )
+        .unwrap()
+    }
+
+    /// Updates the coefficients of the low pull filter with the specified cutoff frequency, gain and Q.
     /// This modifies the peaking EQ characteristics of the low pull filter, influencing the tonal balance
     /// by boosting or cutting frequencies around the specified cutoff. The update is based on the given
     /// sample rate.
 
-    pub fn update_lowpull(&mut self, cutoff: f32, gain: f32, sample_rate: f32) {
-        let fs = sample_rate.hz();
-        let coeffs = biquad::Coefficients::<f32>::from_params(
+    pub fn update_lowpull(&mut self, cutoff: f32, gain: f32, q: f32, sample_rate: f32) {
+        let coeffs = Self::lowpull_coefficients(cutoff, gain, q, sample_rate);
+        self.low_pull.update_coefficients(coeffs);
+    }
+
+    /// The coefficients of the high pull filter, shared with the editor's response display.
+    pub fn highpull_coefficients(
+        cutoff: f32,
+        gain: f32,
+        q: f32,
+        sample_rate: f32,
+    ) -> biquad::Coefficients<f32> {
+        biquad::Coefficients::<f32>::from_params(
             biquad::Type::PeakingEQ(gain),
-            fs,
+            sample_rate.hz(),
             cutoff.hz(),
-            1.2,
+            q,
         )
-        .unwrap();
-        self.low_pull.update_coefficients(coeffs);
+        .unwrap()
     }
 
-    /// Updates the coefficients of the high pull filter with the specified cutoff frequency and gain.
+    /// Updates the coefficients of the high pull filter with the specified cutoff frequency, gain and Q.
     /// This modifies the peaking EQ characteristics of the high pull filter, influencing the tonal balance
     /// by boosting or cutting frequencies around the specified cutoff. The update is based on the given
     /// sample rate.
-    pub fn update_highpull(&mut self, cutoff: f32, gain: f32, sample_rate: f32) {
-        let fs = sample_rate.hz();
-        let coeffs = biquad::Coefficients::<f32>::from_params(
-            biquad::Type::PeakingEQ(gain),
-            fs,
-            cutoff.hz(),
-            1.4,
-        )
-        .unwrap();
+    pub fn update_highpull(&mut self, cutoff: f32, gain: f32, q: f32, sample_rate: f32) {
+        let coeffs = Self::highpull_coefficients(cutoff, gain, q, sample_rate);
         self.high_pull.update_coefficients(coeffs);
     }
 
@@ -265,35 +313,53 @@ impl PushEq {
         }
     }
 
-    /// Updates the coefficients of the overtone push filter with the specified cutoff frequency and gain.
+    /// The coefficients of the overtone push filter, shared with the editor's response display.
+    pub fn overtone_push_coefficients(
+        cutoff: f32,
+        gain: f32,
+        q: f32,
+        sample_rate: f32,
+    ) -> biquad::Coefficients<f32> {
+        biquad::Coefficients::<f32>::from_params(
+            biquad::Type::PeakingEQ(gain),
+            sample_rate.hz(),
+            cutoff.hz(),
+            q,
+        )
+        .unwrap()
+    }
+
+    /// Updates the coefficients of the overtone push filter with the specified cutoff frequency, gain and Q.
     /// This modifies the peaking EQ characteristics of the overtone push filter, influencing the tonal balance
     /// by boosting or cutting frequencies around the specified cutoff. The update is based on the given
     /// sample rate.
-    pub fn update_overtone_push(&mut self, cutoff: f32, gain: f32, sample_rate: f32) {
-        let fs = sample_rate.hz();
-        let coeffs = biquad::Coefficients::<f32>::from_params(
+    pub fn update_overtone_push(&mut self, cutoff: f32, gain: f32, q: f32, sample_rate: f32) {
+        let coeffs = Self::overtone_push_coefficients(cutoff, gain, q, sample_rate);
+        self.overtone_push.update_coefficients(coeffs);
+    }
+
+    /// The coefficients of the tonal push filter, shared with the editor's response display.
+    pub fn tonal_push_coefficients(
+        cutoff: f32,
+        gain: f32,
+        q: f32,
+        sample_rate: f32,
+    ) -> biquad::Coefficients<f32> {
+        biquad::Coefficients::<f32>::from_params(
             biquad::Type::PeakingEQ(gain),
-            fs,
+            sample_rate.hz(),
             cutoff.hz(),
-            1.2,
+            q,
         )
-        .unwrap();
-        self.overtone_push.update_coefficients(coeffs);
+        .unwrap()
     }
 
-    /// Updates the coefficients of the tonal push filter with the specified cutoff frequency and gain.
+    /// Updates the coefficients of the tonal push filter with the specified cutoff frequency, gain and Q.
     /// This modifies the peaking EQ characteristics of the tonal push filter, influencing the tonal balance
     /// by boosting or cutting frequencies around the specified cutoff. The update is based on the given
     /// sample rate.
-    pub fn update_tonal_push(&mut self, cutoff: f32, gain: f32, sample_rate: f32) {
-        let fs = sample_rate.hz();
-        let coeffs = biquad::Coefficients::<f32>::from_params(
-            biquad::Type::PeakingEQ(gain),
-            fs,
-            cutoff.hz(),
-            1.0,
-        )
-        .unwrap();
+    pub fn update_tonal_push(&mut self, cutoff: f32, gain: f32, q: f32, sample_rate: f32) {
+        let coeffs = Self::tonal_push_coefficients(cutoff, gain, q, sample_rate);
         self.tonal_push.update_coefficients(coeffs);
     }
 
@@ -1,4 +1,4 @@
-use crate::device;
+use crate::device::{self, DEFAULT_NUM_CHANNELS};
 use crate::eq::FilterSlope;
 use crate::{
     eq::{InputEq, PullEq, PushEq},
@@ -10,6 +10,7 @@ use nih_plug::buffer::Buffer;
 use nih_plug::prelude::*;
 
 pub struct EqDevice {
+    sample_rate: f32,
     input_gain: gain::GainStage,
     input_eq: Vec<InputEq>,
     pull_eq: Vec<PullEq>,
@@ -17,23 +18,22 @@ pub struct EqDevice {
     push_eq: Vec<PushEq>,
 }
 
-const NUM_CHANNELS: i8 = 2;
-
 impl EqDevice {
     /// Creates a new instance of `EqDevice` with initialized gain stages, equalization stages,
     /// and parameter settings based on the specified sample rate.
 
     pub fn new(sample_rate: f32) -> Self {
         EqDevice {
+            sample_rate,
             input_gain: gain::GainStage::new(),
-            input_eq: (0..NUM_CHANNELS)
+            input_eq: (0..DEFAULT_NUM_CHANNELS)
                 .map(|_| InputEq::new(sample_rate))
                 .collect(),
-            pull_eq: (0..NUM_CHANNELS)
+            pull_eq: (0..DEFAULT_NUM_CHANNELS)
                 .map(|_| PullEq::new(sample_rate))
                 .collect(),
             push_gain: gain::GainStage::new(),
-            push_eq: (0..NUM_CHANNELS)
+            push_eq: (0..DEFAULT_NUM_CHANNELS)
                 .map(|_| PushEq::new(sample_rate))
                 .collect(),
         }
@@ -63,6 +63,7 @@ impl Device for EqDevice {
     /// the current parameter values. This should be called whenever the sample rate changes or
     /// the user changes a parameter value.
     fn update(&mut self, sample_rate: f32, _eq_params: &EqDeviceParams) {
+        self.sample_rate = sample_rate;
         self.input_gain.update(_eq_params.input_gain.value());
         self.input_eq.iter_mut().for_each(|input| {
             input.update_highpass(_eq_params.input_eq_highpass.value(), sample_rate, _eq_params.input_eq_highpass_mode.value());
@@ -72,16 +73,19 @@ impl Device for EqDevice {
             pull.update_lowshelf(
                 _eq_params.pull_lowshelf.value(),
                 _eq_params.pull_lowshelf_gain.value(),
+                _eq_params.pull_lowshelf_q.value(),
                 sample_rate,
             );
             pull.update_lowpull(
                 _eq_params.pull_lowpull.value(),
                 _eq_params.pull_lowpull_gain.value(),
+                _eq_params.pull_lowpull_q.value(),
                 sample_rate,
             );
             pull.update_highpull(
                 _eq_params.pull_highpull.value(),
                 _eq_params.pull_highpull_gain.value(),
+                _eq_params.pull_highpull_q.value(),
                 sample_rate,
             );
         });
@@ -90,11 +94,13 @@ impl Device for EqDevice {
             push.update_overtone_push(
                 _eq_params.push_overtone_push.value(),
                 _eq_params.push_overtone_push_gain.value(),
+                _eq_params.push_overtone_push_q.value(),
                 sample_rate,
             );
             push.update_tonal_push(
                 _eq_params.push_tonal_push.value(),
                 _eq_params.push_tonal_push_gain.value(),
+                _eq_params.push_tonal_push_q.value(),
                 sample_rate,
             );
         });
@@ -105,6 +111,15 @@ impl Device for EqDevice {
         self.pull_eq.iter_mut().for_each(|pull| pull.reset());
         self.push_eq.iter_mut().for_each(|push| push.reset());
     }
+
+    /// New channels start with default coefficients, the next `update()` call brings them in
+    /// line with the current parameter values.
+    fn set_num_channels(&mut self, num_channels: usize) {
+        let sample_rate = self.sample_rate;
+        self.input_eq.resize_with(num_channels, || InputEq::new(sample_rate));
+        self.pull_eq.resize_with(num_channels, || PullEq::new(sample_rate));
+        self.push_eq.resize_with(num_channels, || PushEq::new(sample_rate));
+    }
 }
 
 #[derive(Params)]
@@ -123,24 +138,48 @@ pub struct EqDeviceParams {
     pub pull_lowshelf: FloatParam,
     #[id = "pull_lowshelf_gain"]
     pub pull_lowshelf_gain: FloatParam,
+    #[id = "pull_lowshelf_q"]
+    pub pull_lowshelf_q: FloatParam,
     #[id = "pull_lowpull"]
     pub pull_lowpull: FloatParam,
     #[id = "pull_lowpull_gain"]
     pub pull_lowpull_gain: FloatParam,
+    #[id = "pull_lowpull_q"]
+    pub pull_lowpull_q: FloatParam,
     #[id = "pull_highpull"]
     pub pull_highpull: FloatParam,
     #[id = "pull_highpull_gain"]
     pub pull_highpull_gain: FloatParam,
+    #[id = "pull_highpull_q"]
+    pub pull_highpull_q: FloatParam,
     #[id = "push_gain"]
     pub push_gain: FloatParam,
     #[id = "push_overtone_push"]
     pub push_overtone_push: FloatParam,
     #[id = "push_overtone_push_gain"]
     pub push_overtone_push_gain: FloatParam,
+    #[id = "push_overtone_push_q"]
+    pub push_overtone_push_q: FloatParam,
     #[id = "push_tonal_push"]
     pub push_tonal_push: FloatParam,
     #[id = "push_tonal_push_gain"]
     pub push_tonal_push_gain: FloatParam,
+    #[id = "push_tonal_push_q"]
+    pub push_tonal_push_q: FloatParam,
+}
+
+// Q range of the Pull and Push bands, the defaults match the original fixed values
+fn q_param(name: &str, default: f32) -> FloatParam {
+    FloatParam::new(
+        name,
+        default,
+        FloatRange::Skewed {
+            min: 0.3,
+            max: 6.0,
+            factor: FloatRange::skew_factor(-1.0),
+        },
+    )
+    .with_value_to_string(formatters::v2s_f32_rounded(2))
 }
 
 impl EqDeviceParams {
@@ -265,6 +304,11 @@ impl EqDeviceParams {
                     factor: FloatRange::gain_skew_factor(-7.0, 7.0),
                 },
             ),
+            pull_lowshelf_q: q_param("EQ:Pull:LowShelf:Q", biquad::Q_BUTTERWORTH_F32),
+            pull_lowpull_q: q_param("EQ:Pull:LowPull:Q", 1.2),
+            pull_highpull_q: q_param("EQ:Pull:HighPull:Q", 1.4),
+            push_overtone_push_q: q_param("EQ:Push:Overtone:Q", 1.2),
+            push_tonal_push_q: q_param("EQ:Push:Tonal:Q", 1.0),
             input_eq_lowpass_mode: EnumParam::new("Input:Trim:HighCut:Mode", FilterSlope::Slope48DB),
             input_eq_highpass_mode: EnumParam::new("Input:Trim:LowCut:Mode", FilterSlope::Slope48DB),
         }
@@ -0,0 +1,151 @@
+use nih_plug::prelude::util;
+use std::f64::consts::PI;
+
+use crate::eq::{InputEq, PullEq, PushEq};
+use crate::eq_device::EqDeviceParams;
+
+/// One filter of the EQ, a cascade of identical biquad stages.
+pub struct EqBand {
+    coefficients: biquad::Coefficients<f32>,
+    num_stages: usize,
+}
+
+impl EqBand {
+    fn new(coefficients: biquad::Coefficients<f32>, num_stages: usize) -> Self {
+        Self {
+            coefficients,
+            num_stages,
+        }
+    }
+
+    /// The complex response at `frequency` as `(magnitude, phase in radians)`.
+    pub fn response(&self, frequency: f32, sample_rate: f32) -> (f32, f32) {
+        let c = &self.coefficients;
+        let omega = 2.0 * PI * frequency as f64 / sample_rate as f64;
+        // z^-1 and z^-2 on the unit circle
+        let (z1_re, z1_im) = (omega.cos(), -omega.sin());
+        let (z2_re, z2_im) = ((2.0 * omega).cos(), -(2.0 * omega).sin());
+
+        let num_re = c.b0 as f64 + c.b1 as f64 * z1_re + c.b2 as f64 * z2_re;
+        let num_im = c.b1 as f64 * z1_im + c.b2 as f64 * z2_im;
+        let den_re = 1.0 + c.a1 as f64 * z1_re + c.a2 as f64 * z2_re;
+        let den_im = c.a1 as f64 * z1_im + c.a2 as f64 * z2_im;
+
+        let magnitude = (num_re.hypot(num_im) / den_re.hypot(den_im)).powi(self.num_stages as i32);
+        let phase = (num_im.atan2(num_re) - den_im.atan2(den_re)) * self.num_stages as f64;
+        (magnitude as f32, phase as f32)
+    }
+
+    pub fn magnitude_db(&self, frequency: f32, sample_rate: f32) -> f32 {
+        util::gain_to_db(self.response(frequency, sample_rate).0.max(util::MINUS_INFINITY_GAIN))
+    }
+}
+
+/// The response of the whole EQ device for a set of parameter values, built from the same
+/// coefficients the audio thread uses.
+pub struct EqResponse {
+    sample_rate: f32,
+    // The input and push trims
+    gain: f32,
+    bands: Vec<EqBand>,
+}
+
+impl EqResponse {
+    pub fn new(params: &EqDeviceParams, sample_rate: f32) -> Self {
+        let input_stages = InputEq::num_stages();
+        let bands = vec![
+            EqBand::new(
+                InputEq::highpass_coefficients(
+                    params.input_eq_highpass.value(),
+                    sample_rate,
+                    params.input_eq_highpass_mode.value(),
+                ),
+                input_stages,
+            ),
+            EqBand::new(
+                InputEq::lowpass_coefficients(
+                    params.input_eq_lowpass.value(),
+                    sample_rate,
+                    params.input_eq_lowpass_mode.value(),
+                ),
+                input_stages,
+            ),
+            EqBand::new(
+                PullEq::lowshelf_coefficients(
+                    params.pull_lowshelf.value(),
+                    params.pull_lowshelf_gain.value(),
+                    params.pull_lowshelf_q.value(),
+                    sample_rate,
+                ),
+                1,
+            ),
+            EqBand::new(
+                PullEq::lowpull_coefficients(
+                    params.pull_lowpull.value(),
+                    params.pull_lowpull_gain.value(),
+                    params.pull_lowpull_q.value(),
+                    sample_rate,
+                ),
+                1,
+            ),
+            EqBand::new(
+                PullEq::highpull_coefficients(
+                    params.pull_highpull.value(),
+                    params.pull_highpull_gain.value(),
+                    params.pull_highpull_q.value(),
+                    sample_rate,
+                ),
+                1,
+            ),
+            EqBand::new(
+                PushEq::overtone_push_coefficients(
+                    params.push_overtone_push.value(),
+                    params.push_overtone_push_gain.value(),
+                    params.push_overtone_push_q.value(),
+                    sample_rate,
+                ),
+                1,
+            ),
+            EqBand::new(
+                PushEq::tonal_push_coefficients(
+                    params.push_tonal_push.value(),
+                    params.push_tonal_push_gain.value(),
+                    params.push_tonal_push_q.value(),
+                    sample_rate,
+                ),
+                1,
+            ),
+        ];
+
+        Self {
+            sample_rate,
+            gain: params.input_gain.value() * params.push_gain.value(),
+            bands,
+        }
+    }
+
+    pub fn bands(&self) -> &[EqBand] {
+        &self.bands
+    }
+
+    /// The combined magnitude in dB at `frequency`, including the trims.
+    pub fn magnitude_db(&self, frequency: f32) -> f32 {
+        let magnitude = self
+            .bands
+            .iter()
+            .map(|band| band.response(frequency, self.sample_rate).0)
+            .product::<f32>()
+            * self.gain;
+        util::gain_to_db(magnitude.max(util::MINUS_INFINITY_GAIN))
+    }
+
+    /// The combined phase at `frequency`, wrapped to -pi..pi.
+    pub fn phase(&self, frequency: f32) -> f32 {
+        let phase = self
+            .bands
+            .iter()
+            .map(|band| band.response(frequency, self.sample_rate).1)
+            .sum::<f32>();
+        (phase + std::f32::consts::PI).rem_euclid(2.0 * std::f32::consts::PI) - std::f32::consts::PI
+    }
+}
@@ -0,0 +1,117 @@
+use biquad::{self, Biquad, ToHertz};
+
+// Envelope follower used to normalize the band before the Chebyshev polynomials
+const EXCITER_ATTACK_TIME: f32 = 0.0005;
+const EXCITER_RELEASE_TIME: f32 = 0.02;
+// Harmonic level at full Amount, relative to the highpassed band
+const EXCITER_MAX_AMOUNT: f32 = 0.5;
+
+/// Harmonic exciter. The signal above the corner frequency is normalized by its envelope
+/// and fed through the Chebyshev polynomials T2 and T3, which turn a full scale sine into
+/// exactly its 2nd and 3rd harmonic. The harmonics are scaled back to the band's level,
+/// highpassed again and mixed in on top of the dry signal.
+pub struct Exciter {
+    sample_rate: f32,
+    frequency: f32,
+    // Balance between 2nd (0.0) and 3rd (1.0) harmonic
+    harmonics: f32,
+    amount: f32,
+    band_filter: biquad::DirectForm1<f32>,
+    harmonics_filter: biquad::DirectForm1<f32>,
+    envelope: f32,
+    attack_coeff: f32,
+    release_coeff: f32,
+}
+
+impl Exciter {
+    pub fn new(sample_rate: f32) -> Self {
+        let frequency = 6000.0;
+        let mut exciter = Self {
+            sample_rate,
+            frequency,
+            harmonics: 0.3,
+            amount: 0.5,
+            band_filter: biquad::DirectForm1::new(Self::highpass_coeffs(frequency, sample_rate)),
+            harmonics_filter: biquad::DirectForm1::new(Self::highpass_coeffs(
+                frequency,
+                sample_rate,
+            )),
+            envelope: 0.0,
+            attack_coeff: 0.0,
+            release_coeff: 0.0,
+        };
+        exciter.update_coefficients();
+        exciter
+    }
+
+    fn highpass_coeffs(frequency: f32, sample_rate: f32) -> biquad::Coefficients<f32> {
+        biquad::Coefficients::<f32>::from_params(
+            biquad::Type::HighPass,
+            sample_rate.hz(),
+            frequency.min(sample_rate * 0.45).hz(),
+            biquad::Q_BUTTERWORTH_F32,
+        )
+        .unwrap()
+    }
+
+    fn update_coefficients(&mut self) {
+        let coeffs = Self::highpass_coeffs(self.frequency, self.sample_rate);
+        self.band_filter.update_coefficients(coeffs);
+        self.harmonics_filter.update_coefficients(coeffs);
+        self.attack_coeff = 1.0 - (-1.0 / (EXCITER_ATTACK_TIME * self.sample_rate)).exp();
+        self.release_coeff = 1.0 - (-1.0 / (EXCITER_RELEASE_TIME * self.sample_rate)).exp();
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        if self.sample_rate != sample_rate {
+            self.sample_rate = sample_rate;
+            self.update_coefficients();
+        }
+    }
+
+    pub fn set_frequency(&mut self, frequency: f32) {
+        if self.frequency != frequency {
+            self.frequency = frequency;
+            self.update_coefficients();
+        }
+    }
+
+    /// Sets the balance between even (0.0) and odd (1.0) harmonics.
+    pub fn set_harmonics(&mut self, harmonics: f32) {
+        self.harmonics = harmonics.clamp(0.0, 1.0);
+    }
+
+    pub fn set_amount(&mut self, amount: f32) {
+        self.amount = amount.clamp(0.0, 1.0);
+    }
+
+    pub fn process(&mut self, input: f32) -> f32 {
+        let band = self.band_filter.run(input);
+
+        let level = band.abs();
+        let coeff = if level > self.envelope {
+            self.attack_coeff
+        } else {
+            self.release_coeff
+        };
+        self.envelope += coeff * (level - self.envelope);
+
+        if self.envelope <= f32::EPSILON {
+            return input + self.harmonics_filter.run(0.0);
+        }
+
+        // T2(x) = 2x^2 - 1 and T3(x) = 4x^3 - 3x. T2's offset is taken out by the highpass.
+        let x = (band / self.envelope).clamp(-1.0, 1.0);
+        let second = 2.0 * x * x - 1.0;
+        let third = 4.0 * x * x * x - 3.0 * x;
+        let generated = ((1.0 - self.harmonics) * second + self.harmonics * third) * self.envelope;
+
+        input + self.harmonics_filter.run(generated) * self.amount * EXCITER_MAX_AMOUNT
+    }
+
+    pub fn reset(&mut self) {
+        self.band_filter.reset_state();
+        self.harmonics_filter.reset_state();
+        self.envelope = 0.0;
+    }
+}
@@ -0,0 +1,403 @@
+use nih_plug::prelude::*;
+use nih_plug_vizia::vizia::prelude::*;
+use nih_plug_vizia::vizia::vg;
+use nih_plug_vizia::widgets::ParamEvent;
+use std::cell::RefCell;
+use std::f32::consts::PI;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::analyzer::{AnalyzerParams, Spectrum};
+use crate::device::KVPChannelPluginParams;
+use crate::eq::FilterSlope;
+use crate::eq_device::EqDeviceParams;
+use crate::eq_response::EqResponse;
+use crate::meters::Meters;
+use crate::theme::ThemeState;
+use crate::ui::{draw_background, start_refresh_timer, vg_color};
+
+// Frequency and gain range of the display
+const RESPONSE_MIN_HZ: f32 = 20.0;
+const RESPONSE_MAX_HZ: f32 = 20000.0;
+const RESPONSE_RANGE_DB: f32 = 18.0;
+// Pixels between the points the curves are evaluated at
+const RESPONSE_STEP: f32 = 2.0;
+const RESPONSE_REFRESH: Duration = Duration::from_millis(33);
+
+const GRID_FREQUENCIES: [f32; 3] = [100.0, 1000.0, 10000.0];
+const PHASE_COLOR: Color = Color::rgb(120, 160, 200);
+// Range of the analyzer behind the curves, in dBFS
+const SPECTRUM_MIN_DB: f32 = -90.0;
+const SPECTRUM_MAX_DB: f32 = 0.0;
+
+// Size of the band nodes and how close the mouse needs to be to grab one
+const NODE_RADIUS: f32 = 5.0;
+const NODE_GRAB_RADIUS: f32 = 9.0;
+// Normalized Q change per scroll wheel step
+const NODE_Q_STEP: f32 = 0.04;
+
+/// What the scroll wheel changes on a node.
+enum NodeShape<'a> {
+    Slope(&'a EnumParam<FilterSlope>),
+    Q(&'a FloatParam),
+}
+
+/// A draggable handle for one EQ band. Cut filters have no gain and sit on the 0 dB line.
+struct EqNode<'a> {
+    frequency: &'a FloatParam,
+    gain: Option<&'a FloatParam>,
+    shape: NodeShape<'a>,
+}
+
+fn eq_nodes(params: &EqDeviceParams) -> [EqNode<'_>; 7] {
+    [
+        EqNode {
+            frequency: &params.input_eq_highpass,
+            gain: None,
+            shape: NodeShape::Slope(&params.input_eq_highpass_mode),
+        },
+        EqNode {
+            frequency: &params.input_eq_lowpass,
+            gain: None,
+            shape: NodeShape::Slope(&params.input_eq_lowpass_mode),
+        },
+        EqNode {
+            frequency: &params.pull_lowshelf,
+            gain: Some(&params.pull_lowshelf_gain),
+            shape: NodeShape::Q(&params.pull_lowshelf_q),
+        },
+        EqNode {
+            frequency: &params.pull_lowpull,
+            gain: Some(&params.pull_lowpull_gain),
+            shape: NodeShape::Q(&params.pull_lowpull_q),
+        },
+        EqNode {
+            frequency: &params.pull_highpull,
+            gain: Some(&params.pull_highpull_gain),
+            shape: NodeShape::Q(&params.pull_highpull_q),
+        },
+        EqNode {
+            frequency: &params.push_overtone_push,
+            gain: Some(&params.push_overtone_push_gain),
+            shape: NodeShape::Q(&params.push_overtone_push_q),
+        },
+        EqNode {
+            frequency: &params.push_tonal_push,
+            gain: Some(&params.push_tonal_push_gain),
+            shape: NodeShape::Q(&params.push_tonal_push_q),
+        },
+    ]
+}
+
+// Changes a parameter as a single gesture so hosts record it as automation
+fn set_param<P: Param>(cx: &mut EventContext, param: &P, normalized: f32) {
+    cx.emit(ParamEvent::BeginSetParameter(param).upcast());
+    cx.emit(ParamEvent::SetParameterNormalized(param, normalized.clamp(0.0, 1.0)).upcast());
+    cx.emit(ParamEvent::EndSetParameter(param).upcast());
+}
+
+/// The combined magnitude response of the EQ with every filter's own contribution drawn
+/// faintly behind it, on top of the spectrum before and after the EQ. Every band has a node
+/// that can be dragged to change its frequency and gain, scrolling over a node changes its Q
+/// or slope. Clicking the empty display toggles the phase curve.
+pub struct FrequencyResponseView {
+    params: Arc<KVPChannelPluginParams>,
+    meters: Arc<Meters>,
+    theme: Arc<ThemeState>,
+    show_phase: bool,
+    // The node being dragged, as an index into `eq_nodes()`
+    dragging: Option<usize>,
+    // Last known mouse position, mouse down events don't carry one
+    mouse: (f32, f32),
+    // Pre and post EQ analysis, updated whenever the view draws
+    spectra: RefCell<[Spectrum; 2]>,
+}
+
+impl FrequencyResponseView {
+    pub fn new(
+        cx: &mut Context,
+        params: Arc<KVPChannelPluginParams>,
+        meters: Arc<Meters>,
+        theme: Arc<ThemeState>,
+    ) -> Handle<Self> {
+        Self {
+            params,
+            meters,
+            theme,
+            show_phase: false,
+            dragging: None,
+            mouse: (0.0, 0.0),
+            spectra: RefCell::new([Spectrum::new(), Spectrum::new()]),
+        }
+        .build(cx, |cx| start_refresh_timer(cx, RESPONSE_REFRESH))
+    }
+
+    // Logarithmic frequency axis
+    fn x_to_frequency(x: f32, width: f32) -> f32 {
+        RESPONSE_MIN_HZ * (RESPONSE_MAX_HZ / RESPONSE_MIN_HZ).powf(x / width)
+    }
+
+    fn frequency_to_x(frequency: f32, width: f32) -> f32 {
+        (frequency / RESPONSE_MIN_HZ).ln() / (RESPONSE_MAX_HZ / RESPONSE_MIN_HZ).ln() * width
+    }
+
+    fn db_to_y(db: f32, height: f32) -> f32 {
+        let db = db.clamp(-RESPONSE_RANGE_DB, RESPONSE_RANGE_DB);
+        height * 0.5 * (1.0 - db / RESPONSE_RANGE_DB)
+    }
+
+    fn y_to_db(y: f32, height: f32) -> f32 {
+        (1.0 - 2.0 * y / height) * RESPONSE_RANGE_DB
+    }
+
+    fn node_position(node: &EqNode, bounds: BoundingBox) -> (f32, f32) {
+        let x = Self::frequency_to_x(node.frequency.value(), bounds.w);
+        let db = node.gain.map(|gain| gain.value()).unwrap_or(0.0);
+        (bounds.x + x, bounds.y + Self::db_to_y(db, bounds.h))
+    }
+
+    fn node_at(&self, bounds: BoundingBox, (x, y): (f32, f32)) -> Option<usize> {
+        eq_nodes(&self.params.eq_params)
+            .iter()
+            .map(|node| Self::node_position(node, bounds))
+            .enumerate()
+            .map(|(idx, (node_x, node_y))| (idx, (node_x - x).hypot(node_y - y)))
+            .filter(|&(_, distance)| distance <= NODE_GRAB_RADIUS)
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(idx, _)| idx)
+    }
+
+    fn drag_node(&self, cx: &mut EventContext, idx: usize) {
+        let bounds = cx.bounds();
+        let params = self.params.clone();
+        let node = &eq_nodes(&params.eq_params)[idx];
+
+        let frequency = Self::x_to_frequency(self.mouse.0 - bounds.x, bounds.w);
+        cx.emit(ParamEvent::SetParameter(node.frequency, frequency).upcast());
+        if let Some(gain) = node.gain {
+            let db = Self::y_to_db(self.mouse.1 - bounds.y, bounds.h);
+            cx.emit(ParamEvent::SetParameter(gain, db).upcast());
+        }
+    }
+
+    fn scroll_node(&self, cx: &mut EventContext, idx: usize, steps: f32) {
+        let params = self.params.clone();
+        match eq_nodes(&params.eq_params)[idx].shape {
+            NodeShape::Slope(slope) => {
+                // Step through the slopes one at a time, whatever the scroll distance
+                let num_steps = slope.step_count().unwrap_or(1) as f32;
+                let step = slope.unmodulated_normalized_value() * num_steps + steps.signum();
+                set_param(cx, slope, step.round() / num_steps);
+            }
+            NodeShape::Q(q) => {
+                set_param(cx, q, q.unmodulated_normalized_value() + steps * NODE_Q_STEP);
+            }
+        }
+    }
+
+    fn begin_drag(&mut self, cx: &mut EventContext, idx: usize) {
+        let params = self.params.clone();
+        let node = &eq_nodes(&params.eq_params)[idx];
+        cx.emit(ParamEvent::BeginSetParameter(node.frequency).upcast());
+        if let Some(gain) = node.gain {
+            cx.emit(ParamEvent::BeginSetParameter(gain).upcast());
+        }
+        self.dragging = Some(idx);
+        cx.capture();
+    }
+
+    fn end_drag(&mut self, cx: &mut EventContext) {
+        let Some(idx) = self.dragging.take() else {
+            return;
+        };
+        let params = self.params.clone();
+        let node = &eq_nodes(&params.eq_params)[idx];
+        cx.emit(ParamEvent::EndSetParameter(node.frequency).upcast());
+        if let Some(gain) = node.gain {
+            cx.emit(ParamEvent::EndSetParameter(gain).upcast());
+        }
+        cx.release();
+    }
+
+    fn spectrum_db_to_y(db: f32, height: f32) -> f32 {
+        let position = (db - SPECTRUM_MIN_DB) / (SPECTRUM_MAX_DB - SPECTRUM_MIN_DB);
+        height * (1.0 - position.clamp(0.0, 1.0))
+    }
+
+    // A spectrum as a filled area, each column showing the loudest bin it covers
+    fn spectrum_path(
+        bounds: BoundingBox,
+        level_db: impl Fn(f32, f32) -> f32,
+        fill: bool,
+    ) -> vg::Path {
+        let mut path = vg::Path::new();
+        let bottom = bounds.y + bounds.h;
+        if fill {
+            path.move_to(bounds.x, bottom);
+        }
+        let mut x = 0.0;
+        while x <= bounds.w {
+            let low_hz = Self::x_to_frequency(x, bounds.w);
+            let high_hz = Self::x_to_frequency(x + RESPONSE_STEP, bounds.w);
+            let y = bounds.y + Self::spectrum_db_to_y(level_db(low_hz, high_hz), bounds.h);
+            if x == 0.0 && !fill {
+                path.move_to(bounds.x, y);
+            } else {
+                path.line_to(bounds.x + x, y);
+            }
+            x += RESPONSE_STEP;
+        }
+        if fill {
+            path.line_to(bounds.x + bounds.w, bottom);
+            path.close();
+        }
+        path
+    }
+
+    fn draw_spectra(&self, canvas: &mut Canvas, bounds: BoundingBox, sample_rate: f32) {
+        let palette = self.theme.palette();
+        let analyzer: &AnalyzerParams = &self.params.analyzer_params;
+        let mut spectra = self.spectra.borrow_mut();
+        spectra[0].update(&self.meters.pre_eq_spectrum, analyzer);
+        spectra[1].update(&self.meters.post_eq_spectrum, analyzer);
+
+        for (spectrum, color) in spectra.iter().zip([palette.spectrum_pre, palette.spectrum_post]) {
+            let path = Self::spectrum_path(
+                bounds,
+                |low_hz, high_hz| spectrum.level_db(low_hz, high_hz, sample_rate, analyzer),
+                true,
+            );
+            canvas.fill_path(&path, &vg::Paint::color(vg_color(color)));
+        }
+
+        // Held peaks of the EQ'd signal, where resonances stand out
+        if spectra[1].peak_hold() {
+            let path = Self::spectrum_path(
+                bounds,
+                |low_hz, high_hz| {
+                    spectra[1]
+                        .peak_db(low_hz, high_hz, sample_rate, analyzer)
+                        .unwrap_or(SPECTRUM_MIN_DB)
+                },
+                false,
+            );
+            canvas.stroke_path(&path, &vg::Paint::color(vg_color(palette.accent)));
+        }
+    }
+
+    fn curve(bounds: BoundingBox, value_to_y: impl Fn(f32) -> f32) -> vg::Path {
+        let mut path = vg::Path::new();
+        let mut x = 0.0;
+        while x <= bounds.w {
+            let frequency = Self::x_to_frequency(x, bounds.w);
+            let y = bounds.y + value_to_y(frequency);
+            if x == 0.0 {
+                path.move_to(bounds.x, y);
+            } else {
+                path.line_to(bounds.x + x, y);
+            }
+            x += RESPONSE_STEP;
+        }
+        path
+    }
+}
+
+impl View for FrequencyResponseView {
+    fn element(&self) -> Option<&'static str> {
+        Some("frequency-response")
+    }
+
+    fn event(&mut self, cx: &mut EventContext, event: &mut Event) {
+        event.map(|window_event, meta| match *window_event {
+            WindowEvent::MouseDown(MouseButton::Left) => {
+                match self.node_at(cx.bounds(), self.mouse) {
+                    Some(idx) => self.begin_drag(cx, idx),
+                    None => self.show_phase = !self.show_phase,
+                }
+                cx.needs_redraw();
+                meta.consume();
+            }
+            WindowEvent::MouseUp(MouseButton::Left) => {
+                self.end_drag(cx);
+                meta.consume();
+            }
+            WindowEvent::MouseMove(x, y) => {
+                self.mouse = (x, y);
+                if let Some(idx) = self.dragging {
+                    self.drag_node(cx, idx);
+                    cx.needs_redraw();
+                    meta.consume();
+                }
+            }
+            WindowEvent::MouseScroll(_, scroll_y) => {
+                if let Some(idx) = self.node_at(cx.bounds(), self.mouse) {
+                    self.scroll_node(cx, idx, scroll_y);
+                    cx.needs_redraw();
+                    meta.consume();
+                }
+            }
+            _ => {}
+        });
+    }
+
+    fn draw(&self, cx: &mut DrawContext, canvas: &mut Canvas) {
+        let bounds = cx.bounds();
+        if bounds.w == 0.0 || bounds.h == 0.0 {
+            return;
+        }
+        let palette = self.theme.palette();
+
+        draw_background(canvas, bounds, palette);
+
+        let sample_rate = self.meters.sample_rate();
+        self.draw_spectra(canvas, bounds, sample_rate);
+
+        let mut grid = vg::Path::new();
+        for frequency in GRID_FREQUENCIES {
+            let x = bounds.x + Self::frequency_to_x(frequency, bounds.w);
+            grid.move_to(x, bounds.y);
+            grid.line_to(x, bounds.y + bounds.h);
+        }
+        let zero_y = bounds.y + Self::db_to_y(0.0, bounds.h);
+        grid.move_to(bounds.x, zero_y);
+        grid.line_to(bounds.x + bounds.w, zero_y);
+        canvas.stroke_path(&grid, &vg::Paint::color(vg_color(palette.border)));
+
+        let response = EqResponse::new(&self.params.eq_params, sample_rate);
+
+        for band in response.bands() {
+            let path = Self::curve(bounds, |frequency| {
+                Self::db_to_y(band.magnitude_db(frequency, sample_rate), bounds.h)
+            });
+            canvas.stroke_path(&path, &vg::Paint::color(vg_color(palette.accent_muted)));
+        }
+
+        if self.show_phase {
+            let path = Self::curve(bounds, |frequency| {
+                bounds.h * 0.5 * (1.0 - response.phase(frequency) / PI)
+            });
+            canvas.stroke_path(&path, &vg::Paint::color(vg_color(PHASE_COLOR)));
+        }
+
+        let path = Self::curve(bounds, |frequency| {
+            Self::db_to_y(response.magnitude_db(frequency), bounds.h)
+        });
+        let mut paint = vg::Paint::color(vg_color(palette.highlight));
+        paint.set_line_width(2.0);
+        canvas.stroke_path(&path, &paint);
+
+        let hovered = self.dragging.or_else(|| self.node_at(bounds, self.mouse));
+        for (idx, node) in eq_nodes(&self.params.eq_params).iter().enumerate() {
+            let (x, y) = Self::node_position(node, bounds);
+            let mut circle = vg::Path::new();
+            circle.circle(x, y, NODE_RADIUS);
+            let color = if hovered == Some(idx) {
+                palette.text
+            } else {
+                palette.accent
+            };
+            canvas.fill_path(&circle, &vg::Paint::color(vg_color(color)));
+            canvas.stroke_path(&circle, &vg::Paint::color(vg_color(palette.panel)));
+        }
+    }
+}
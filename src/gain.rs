@@ -16,3 +16,63 @@ impl GainStage {
         self.gain = gain
     }
 }
+
+// Window of the RMS detectors, long enough to ignore the waveform but follow the phrasing
+const AUTO_GAIN_RMS_TIME: f32 = 0.3;
+// How quickly the compensation gain follows the measured difference
+const AUTO_GAIN_SMOOTHING_TIME: f32 = 0.05;
+// Below this mean square (-80 dB) the input counts as silence and the gain is held
+const AUTO_GAIN_SILENCE: f32 = 1.0e-8;
+// Limits of the compensation, +-24 dB
+const AUTO_GAIN_MAX: f32 = 16.0;
+
+/// Matches the loudness of a processed signal to its unprocessed version by continuously
+/// comparing the RMS level of both.
+pub struct AutoGain {
+    rms_coeff: f32,
+    smoothing_coeff: f32,
+    input_mean_square: f32,
+    output_mean_square: f32,
+    gain: f32,
+}
+
+impl AutoGain {
+    pub fn new(sample_rate: f32) -> Self {
+        let mut auto_gain = Self {
+            rms_coeff: 0.0,
+            smoothing_coeff: 0.0,
+            input_mean_square: 0.0,
+            output_mean_square: 0.0,
+            gain: 1.0,
+        };
+        auto_gain.set_sample_rate(sample_rate);
+        auto_gain
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.rms_coeff = 1.0 - (-1.0 / (AUTO_GAIN_RMS_TIME * sample_rate)).exp();
+        self.smoothing_coeff = 1.0 - (-1.0 / (AUTO_GAIN_SMOOTHING_TIME * sample_rate)).exp();
+    }
+
+    /// Returns `output` scaled to the level of `input`.
+    pub fn process(&mut self, input: f32, output: f32) -> f32 {
+        self.input_mean_square += self.rms_coeff * (input * input - self.input_mean_square);
+        self.output_mean_square += self.rms_coeff * (output * output - self.output_mean_square);
+
+        if self.input_mean_square > AUTO_GAIN_SILENCE && self.output_mean_square > AUTO_GAIN_SILENCE
+        {
+            let target = (self.input_mean_square / self.output_mean_square)
+                .sqrt()
+                .clamp(1.0 / AUTO_GAIN_MAX, AUTO_GAIN_MAX);
+            self.gain += self.smoothing_coeff * (target - self.gain);
+        }
+
+        output * self.gain
+    }
+
+    pub fn reset(&mut self) {
+        self.input_mean_square = 0.0;
+        self.output_mean_square = 0.0;
+        self.gain = 1.0;
+    }
+}
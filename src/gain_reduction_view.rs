@@ -0,0 +1,130 @@
+use nih_plug_vizia::vizia::prelude::*;
+use nih_plug_vizia::vizia::vg;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::meters::{GainReductionMeter, GR_HISTORY_LEN};
+use crate::theme::ThemeState;
+use crate::ui::{draw_background, start_refresh_timer, vg_color};
+
+// Deepest gain reduction the displays show
+const GR_RANGE_DB: f32 = 24.0;
+const GR_REFRESH: Duration = Duration::from_millis(33);
+
+/// Gain reduction bars for every channel, growing from the right edge to the left.
+pub struct GainReductionView {
+    meter: Arc<GainReductionMeter>,
+    theme: Arc<ThemeState>,
+}
+
+impl GainReductionView {
+    pub fn new(
+        cx: &mut Context,
+        meter: Arc<GainReductionMeter>,
+        theme: Arc<ThemeState>,
+    ) -> Handle<Self> {
+        Self { meter, theme }.build(cx, |cx| start_refresh_timer(cx, GR_REFRESH))
+    }
+}
+
+impl View for GainReductionView {
+    fn element(&self) -> Option<&'static str> {
+        Some("gain-reduction-meter")
+    }
+
+    fn draw(&self, cx: &mut DrawContext, canvas: &mut Canvas) {
+        let bounds = cx.bounds();
+        if bounds.w == 0.0 || bounds.h == 0.0 {
+            return;
+        }
+        let palette = self.theme.palette();
+        draw_background(canvas, bounds, palette);
+
+        let num_channels = self.meter.num_channels();
+        if num_channels == 0 {
+            return;
+        }
+
+        let bar_height = bounds.h / num_channels as f32;
+        for channel in 0..num_channels {
+            let amount = (self.meter.gain_reduction(channel) / GR_RANGE_DB).clamp(0.0, 1.0);
+            let width = (bounds.w - 2.0) * amount;
+
+            let mut bar = vg::Path::new();
+            bar.rect(
+                bounds.x + bounds.w - 1.0 - width,
+                bounds.y + channel as f32 * bar_height + 1.0,
+                width,
+                (bar_height - 2.0).max(1.0),
+            );
+            canvas.fill_path(&bar, &vg::Paint::color(vg_color(palette.accent)));
+        }
+    }
+}
+
+/// A scrolling view of the compressor's output level with the gain reduction curve hanging
+/// from the top edge, newest on the right.
+pub struct GainReductionHistoryView {
+    meter: Arc<GainReductionMeter>,
+    theme: Arc<ThemeState>,
+}
+
+impl GainReductionHistoryView {
+    pub fn new(
+        cx: &mut Context,
+        meter: Arc<GainReductionMeter>,
+        theme: Arc<ThemeState>,
+    ) -> Handle<Self> {
+        Self { meter, theme }.build(cx, |cx| start_refresh_timer(cx, GR_REFRESH))
+    }
+}
+
+impl View for GainReductionHistoryView {
+    fn element(&self) -> Option<&'static str> {
+        Some("gain-reduction-history")
+    }
+
+    fn draw(&self, cx: &mut DrawContext, canvas: &mut Canvas) {
+        let bounds = cx.bounds();
+        if bounds.w == 0.0 || bounds.h == 0.0 {
+            return;
+        }
+        let palette = self.theme.palette();
+        draw_background(canvas, bounds, palette);
+
+        let center = bounds.y + bounds.h / 2.0;
+        let column_width = bounds.w / GR_HISTORY_LEN as f32;
+
+        // The waveform as mirrored level columns
+        let mut waveform = vg::Path::new();
+        for column in 0..GR_HISTORY_LEN {
+            let (level, _) = self.meter.history(column);
+            let half_height = level.clamp(0.0, 1.0) * (bounds.h / 2.0 - 1.0);
+            if half_height > 0.0 {
+                waveform.rect(
+                    bounds.x + column as f32 * column_width,
+                    center - half_height,
+                    column_width.max(1.0),
+                    half_height * 2.0,
+                );
+            }
+        }
+        canvas.fill_path(&waveform, &vg::Paint::color(vg_color(palette.accent_muted)));
+
+        // The gain reduction curve on top of it
+        let mut curve = vg::Path::new();
+        for column in 0..GR_HISTORY_LEN {
+            let (_, gain_reduction) = self.meter.history(column);
+            let x = bounds.x + (column as f32 + 0.5) * column_width;
+            let y = bounds.y + (gain_reduction / GR_RANGE_DB).clamp(0.0, 1.0) * bounds.h;
+            if column == 0 {
+                curve.move_to(x, y);
+            } else {
+                curve.line_to(x, y);
+            }
+        }
+        let mut paint = vg::Paint::color(vg_color(palette.text));
+        paint.set_line_width(1.5);
+        canvas.stroke_path(&curve, &paint);
+    }
+}
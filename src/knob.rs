@@ -0,0 +1,333 @@
+use nih_plug::prelude::Param;
+use nih_plug_vizia::vizia::prelude::*;
+use nih_plug_vizia::vizia::vg;
+use nih_plug_vizia::widgets::param_base::ParamWidgetBase;
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+use crate::theme::ThemeState;
+use crate::ui::vg_color;
+
+// Vertical drag distance in pixels that sweeps the full range
+const DRAG_DISTANCE: f32 = 200.0;
+// Holding shift while dragging slows the knob down by this factor
+const FINE_DRAG_MULTIPLIER: f32 = 0.1;
+// The knob sweeps 270 degrees with the gap at the bottom
+const START_ANGLE: f32 = 0.75 * PI;
+const SWEEP_ANGLE: f32 = 1.5 * PI;
+const ARC_WIDTH: f32 = 3.0;
+const READOUT_HEIGHT: f32 = 16.0;
+
+// Where a drag started, re-anchored whenever shift is pressed or released so the knob doesn't
+// jump when switching between coarse and fine
+struct DragStatus {
+    start_y: f32,
+    start_value: f32,
+    fine: bool,
+}
+
+/// A rotary knob for a parameter with its value and unit below it. Drag vertically to change
+/// the value, with shift for finer steps. Double-click resets to the default, alt-click opens a
+/// text box for typing an exact value and the mouse wheel moves one step per line.
+#[derive(Lens)]
+pub struct ParamKnob {
+    param_base: ParamWidgetBase,
+    #[lens(ignore)]
+    theme: Arc<ThemeState>,
+
+    text_input_active: bool,
+    #[lens(ignore)]
+    drag: Option<DragStatus>,
+    #[lens(ignore)]
+    mouse_y: f32,
+    #[lens(ignore)]
+    scrolled_lines: f32,
+}
+
+enum ParamKnobEvent {
+    CancelTextInput,
+    TextInput(String),
+}
+
+impl ParamKnob {
+    pub fn new<L, Params, P, FMap>(
+        cx: &mut Context,
+        params: L,
+        params_to_param: FMap,
+        theme: Arc<ThemeState>,
+    ) -> Handle<Self>
+    where
+        L: Lens<Target = Params> + Clone,
+        Params: 'static,
+        P: Param + 'static,
+        FMap: Fn(&Params) -> &P + Copy + 'static,
+    {
+        Self {
+            param_base: ParamWidgetBase::new(cx, params.clone(), params_to_param),
+            theme,
+
+            text_input_active: false,
+            drag: None,
+            mouse_y: 0.0,
+            scrolled_lines: 0.0,
+        }
+        .build(
+            cx,
+            ParamWidgetBase::build_view(params, params_to_param, move |cx, param_data| {
+                let display_value_lens = param_data.make_lens(|param| {
+                    param.normalized_value_to_string(param.unmodulated_normalized_value(), true)
+                });
+
+                // The readout turns into a text box while typing in a value
+                Binding::new(cx, ParamKnob::text_input_active, move |cx, text_input_active| {
+                    if text_input_active.get(cx) {
+                        Textbox::new(cx, display_value_lens.clone())
+                            .on_submit(|cx, string, success| {
+                                if success {
+                                    cx.emit(ParamKnobEvent::TextInput(string))
+                                } else {
+                                    cx.emit(ParamKnobEvent::CancelTextInput)
+                                }
+                            })
+                            .on_build(|cx| {
+                                cx.emit(TextEvent::StartEdit);
+                                cx.emit(TextEvent::SelectAll);
+                            })
+                            .class("knob-entry")
+                            .width(Stretch(1.0))
+                            .height(Pixels(READOUT_HEIGHT))
+                            .top(Stretch(1.0));
+                    } else {
+                        Label::new(cx, display_value_lens.clone())
+                            .class("knob-readout")
+                            .width(Stretch(1.0))
+                            .height(Pixels(READOUT_HEIGHT))
+                            .top(Stretch(1.0))
+                            .hoverable(false);
+                    }
+                });
+            }),
+        )
+        .width(Pixels(64.0))
+        .height(Pixels(64.0))
+        .cursor(CursorIcon::NsResize)
+    }
+
+    fn normalized_to_angle(normalized: f32) -> f32 {
+        START_ANGLE + SWEEP_ANGLE * normalized.clamp(0.0, 1.0)
+    }
+
+    fn set_value(&self, cx: &mut EventContext, normalized: f32) {
+        self.param_base.set_normalized_value(cx, normalized.clamp(0.0, 1.0));
+    }
+
+    fn reset_to_default(&self, cx: &mut EventContext) {
+        self.param_base.begin_set_parameter(cx);
+        self.set_value(cx, self.param_base.default_normalized_value());
+        self.param_base.end_set_parameter(cx);
+    }
+
+    fn begin_drag(&mut self, cx: &mut EventContext) {
+        self.drag = Some(DragStatus {
+            start_y: self.mouse_y,
+            start_value: self.param_base.unmodulated_normalized_value(),
+            fine: cx.modifiers().shift(),
+        });
+        cx.capture();
+        cx.focus();
+        cx.set_active(true);
+        self.param_base.begin_set_parameter(cx);
+    }
+
+    fn drag_to(&mut self, cx: &mut EventContext) {
+        let fine = cx.modifiers().shift();
+        let current_value = self.param_base.unmodulated_normalized_value();
+        let mouse_y = self.mouse_y;
+        let Some(drag) = self.drag.as_mut() else {
+            return;
+        };
+        if drag.fine != fine {
+            *drag = DragStatus {
+                start_y: mouse_y,
+                start_value: current_value,
+                fine,
+            };
+        }
+
+        let multiplier = if fine { FINE_DRAG_MULTIPLIER } else { 1.0 };
+        let value = drag.start_value + (drag.start_y - mouse_y) / DRAG_DISTANCE * multiplier;
+        self.set_value(cx, value);
+    }
+
+    fn end_drag(&mut self, cx: &mut EventContext) {
+        if self.drag.take().is_some() {
+            cx.release();
+            cx.set_active(false);
+            self.param_base.end_set_parameter(cx);
+        }
+    }
+
+    fn scroll(&mut self, cx: &mut EventContext, scroll_y: f32) {
+        self.scrolled_lines += scroll_y;
+        if self.scrolled_lines.abs() < 1.0 {
+            return;
+        }
+
+        let finer = cx.modifiers().shift();
+        let dragging = self.drag.is_some();
+        if !dragging {
+            self.param_base.begin_set_parameter(cx);
+        }
+        let mut value = self.param_base.unmodulated_normalized_value();
+        while self.scrolled_lines >= 1.0 {
+            value = self.param_base.next_normalized_step(value, finer);
+            self.scrolled_lines -= 1.0;
+        }
+        while self.scrolled_lines <= -1.0 {
+            value = self.param_base.previous_normalized_step(value, finer);
+            self.scrolled_lines += 1.0;
+        }
+        self.set_value(cx, value);
+        if !dragging {
+            self.param_base.end_set_parameter(cx);
+        }
+    }
+}
+
+impl View for ParamKnob {
+    fn element(&self) -> Option<&'static str> {
+        Some("param-knob")
+    }
+
+    fn event(&mut self, cx: &mut EventContext, event: &mut Event) {
+        event.map(|knob_event, meta| match knob_event {
+            ParamKnobEvent::CancelTextInput => {
+                self.text_input_active = false;
+                cx.set_active(false);
+                meta.consume();
+            }
+            ParamKnobEvent::TextInput(string) => {
+                if let Some(normalized) = self.param_base.string_to_normalized_value(string) {
+                    self.param_base.begin_set_parameter(cx);
+                    self.set_value(cx, normalized);
+                    self.param_base.end_set_parameter(cx);
+                }
+                self.text_input_active = false;
+                meta.consume();
+            }
+        });
+
+        event.map(|window_event, meta| match *window_event {
+            WindowEvent::MouseDown(MouseButton::Left) => {
+                if cx.modifiers().alt() {
+                    self.text_input_active = true;
+                    cx.set_active(true);
+                } else if !self.text_input_active {
+                    self.begin_drag(cx);
+                }
+                meta.consume();
+            }
+            WindowEvent::MouseDoubleClick(MouseButton::Left) => {
+                if !self.text_input_active {
+                    self.end_drag(cx);
+                    self.reset_to_default(cx);
+                }
+                meta.consume();
+            }
+            WindowEvent::MouseUp(MouseButton::Left) => {
+                if self.drag.is_some() {
+                    self.end_drag(cx);
+                    meta.consume();
+                }
+            }
+            WindowEvent::MouseMove(_, y) => {
+                self.mouse_y = y;
+                if self.drag.is_some() {
+                    self.drag_to(cx);
+                    meta.consume();
+                }
+            }
+            // Pressing or releasing shift mid-drag re-anchors the drag
+            WindowEvent::KeyDown(Code::ShiftLeft | Code::ShiftRight, _)
+            | WindowEvent::KeyUp(Code::ShiftLeft | Code::ShiftRight, _) => {
+                self.drag_to(cx);
+            }
+            WindowEvent::MouseScroll(_, scroll_y) => {
+                if !self.text_input_active {
+                    self.scroll(cx, scroll_y);
+                    meta.consume();
+                }
+            }
+            _ => {}
+        });
+    }
+
+    fn draw(&self, cx: &mut DrawContext, canvas: &mut Canvas) {
+        let bounds = cx.bounds();
+        let knob_height = bounds.h - READOUT_HEIGHT;
+        if bounds.w == 0.0 || knob_height <= 0.0 {
+            return;
+        }
+        let palette = self.theme.palette();
+
+        let radius = (bounds.w.min(knob_height) / 2.0 - ARC_WIDTH).max(1.0);
+        let (center_x, center_y) = (bounds.x + bounds.w / 2.0, bounds.y + knob_height / 2.0);
+
+        let mut face = vg::Path::new();
+        face.circle(center_x, center_y, radius * 0.72);
+        canvas.fill_path(&face, &vg::Paint::color(vg_color(palette.panel)));
+        canvas.stroke_path(&face, &vg::Paint::color(vg_color(palette.border)));
+
+        let mut track = vg::Path::new();
+        track.arc(
+            center_x,
+            center_y,
+            radius,
+            START_ANGLE,
+            START_ANGLE + SWEEP_ANGLE,
+            vg::Solidity::Hole,
+        );
+        let mut track_paint = vg::Paint::color(vg_color(palette.border));
+        track_paint.set_line_width(ARC_WIDTH);
+        track_paint.set_line_cap(vg::LineCap::Round);
+        canvas.stroke_path(&track, &track_paint);
+
+        // The value arc runs from the default, so bipolar controls grow out from the middle
+        let value_angle = Self::normalized_to_angle(self.param_base.modulated_normalized_value());
+        let default_angle = Self::normalized_to_angle(self.param_base.default_normalized_value());
+        if (value_angle - default_angle).abs() > f32::EPSILON {
+            let mut value_arc = vg::Path::new();
+            value_arc.arc(
+                center_x,
+                center_y,
+                radius,
+                value_angle.min(default_angle),
+                value_angle.max(default_angle),
+                vg::Solidity::Hole,
+            );
+            let mut value_paint = vg::Paint::color(vg_color(palette.highlight));
+            value_paint.set_line_width(ARC_WIDTH);
+            value_paint.set_line_cap(vg::LineCap::Round);
+            canvas.stroke_path(&value_arc, &value_paint);
+        }
+
+        let mut pointer = vg::Path::new();
+        pointer.move_to(
+            center_x + value_angle.cos() * radius * 0.25,
+            center_y + value_angle.sin() * radius * 0.25,
+        );
+        pointer.line_to(
+            center_x + value_angle.cos() * radius * 0.72,
+            center_y + value_angle.sin() * radius * 0.72,
+        );
+        let color = if self.drag.is_some() {
+            palette.text
+        } else {
+            palette.accent
+        };
+        let mut pointer_paint = vg::Paint::color(vg_color(color));
+        pointer_paint.set_line_width(2.0);
+        pointer_paint.set_line_cap(vg::LineCap::Round);
+        canvas.stroke_path(&pointer, &pointer_paint);
+    }
+}
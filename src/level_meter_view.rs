@@ -0,0 +1,108 @@
+use nih_plug::prelude::util;
+use nih_plug_vizia::vizia::prelude::*;
+use nih_plug_vizia::vizia::vg;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::meters::LevelMeter;
+use crate::theme::ThemeState;
+use crate::ui::{draw_background, start_refresh_timer, vg_color};
+
+// Range of the meter scale
+const METER_MIN_DB: f32 = -60.0;
+const METER_MAX_DB: f32 = 6.0;
+// How often the meter redraws
+const METER_REFRESH: Duration = Duration::from_millis(33);
+// Width of the clip indicator at the end of each bar
+const CLIP_WIDTH: f32 = 8.0;
+
+const CLIP_COLOR: Color = Color::rgb(230, 40, 30);
+
+/// Horizontal peak, peak hold and RMS bars for every channel of a `LevelMeter`, with a clip
+/// indicator at the end of each bar. Clicking the meter clears the clip indicators.
+pub struct LevelMeterView {
+    meter: Arc<LevelMeter>,
+    theme: Arc<ThemeState>,
+}
+
+impl LevelMeterView {
+    pub fn new(cx: &mut Context, meter: Arc<LevelMeter>, theme: Arc<ThemeState>) -> Handle<Self> {
+        Self { meter, theme }.build(cx, |cx| start_refresh_timer(cx, METER_REFRESH))
+    }
+
+    fn db_to_position(level: f32) -> f32 {
+        let db = util::gain_to_db(level.max(util::MINUS_INFINITY_GAIN));
+        ((db - METER_MIN_DB) / (METER_MAX_DB - METER_MIN_DB)).clamp(0.0, 1.0)
+    }
+}
+
+impl View for LevelMeterView {
+    fn element(&self) -> Option<&'static str> {
+        Some("level-meter")
+    }
+
+    fn event(&mut self, _cx: &mut EventContext, event: &mut Event) {
+        event.map(|window_event, meta| {
+            if let WindowEvent::MouseDown(MouseButton::Left) = window_event {
+                self.meter.reset_clip();
+                meta.consume();
+            }
+        });
+    }
+
+    fn draw(&self, cx: &mut DrawContext, canvas: &mut Canvas) {
+        let bounds = cx.bounds();
+        if bounds.w == 0.0 || bounds.h == 0.0 {
+            return;
+        }
+        let palette = self.theme.palette();
+
+        draw_background(canvas, bounds, palette);
+
+        let num_channels = self.meter.num_channels();
+        if num_channels == 0 {
+            return;
+        }
+
+        let bar_width = bounds.w - CLIP_WIDTH - 2.0;
+        let bar_height = bounds.h / num_channels as f32;
+        for channel in 0..num_channels {
+            let y = bounds.y + channel as f32 * bar_height + 1.0;
+            let height = (bar_height - 2.0).max(1.0);
+
+            let mut peak = vg::Path::new();
+            peak.rect(
+                bounds.x + 1.0,
+                y,
+                bar_width * Self::db_to_position(self.meter.peak(channel)),
+                height,
+            );
+            canvas.fill_path(&peak, &vg::Paint::color(vg_color(palette.highlight)));
+
+            let mut rms = vg::Path::new();
+            rms.rect(
+                bounds.x + 1.0,
+                y,
+                bar_width * Self::db_to_position(self.meter.rms(channel)),
+                height,
+            );
+            canvas.fill_path(&rms, &vg::Paint::color(vg_color(palette.accent)));
+
+            let hold_x =
+                bounds.x + 1.0 + bar_width * Self::db_to_position(self.meter.hold(channel));
+            let mut hold = vg::Path::new();
+            hold.move_to(hold_x, y);
+            hold.line_to(hold_x, y + height);
+            canvas.stroke_path(&hold, &vg::Paint::color(vg_color(palette.text)));
+
+            let clip_color = if self.meter.clipped(channel) {
+                CLIP_COLOR
+            } else {
+                palette.border
+            };
+            let mut clip = vg::Path::new();
+            clip.rect(bounds.x + bounds.w - CLIP_WIDTH - 1.0, y, CLIP_WIDTH, height);
+            canvas.fill_path(&clip, &vg::Paint::color(vg_color(clip_color)));
+        }
+    }
+}
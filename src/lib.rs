@@ -7,11 +7,39 @@ use nih_plug::{
 mod device;
 mod eq;
 mod gain;
+mod delay;
 mod compressor;
 mod colorizer;
 mod colorizor_device;
 mod eq_device;
+mod eq_response;
 mod compressor_device;
+mod console;
+mod console_device;
+mod seed_registry;
+mod oversampler;
+mod adaa;
+mod saturation;
+mod emphasis;
+mod dc_blocker;
+mod exciter;
+mod lofi;
+mod tape;
+mod meters;
+mod analyzer;
+mod loudness;
+mod preset;
+mod ab;
+mod level_meter_view;
+mod gain_reduction_view;
+mod transfer_curve_view;
+mod saturation_view;
+mod loudness_view;
+mod frequency_response_view;
+mod knob;
+mod theme;
+mod preset_browser;
+mod ab_switch;
 mod ui;
 
 impl ClapPlugin for KVPChannelPlugin {
@@ -0,0 +1,193 @@
+use biquad::{self, Biquad, ToHertz};
+use nih_plug::prelude::Enum;
+
+// Crackle clicks decay within a couple of milliseconds
+const CRACKLE_DECAY_TIME: f32 = 0.002;
+// Clicks per second at full crackle
+const CRACKLE_MAX_DENSITY: f32 = 40.0;
+
+#[derive(Copy, Clone, Enum, PartialEq)]
+pub enum LoFiFilter {
+    Off,
+    Telephone,
+    Radio,
+}
+
+impl LoFiFilter {
+    // Lower and upper corner of the band that's let through
+    fn band(&self) -> (f32, f32) {
+        match self {
+            LoFiFilter::Off => (20.0, 20000.0),
+            LoFiFilter::Telephone => (300.0, 3400.0),
+            LoFiFilter::Radio => (150.0, 5000.0),
+        }
+    }
+}
+
+/// Lo-fi degradation: a telephone or radio band, sample-and-hold decimation, bit depth
+/// reduction with optional TPDF dither and vinyl-style crackle. The decimation doesn't
+/// filter on purpose, the aliasing is part of the sound.
+pub struct LoFi {
+    sample_rate: f32,
+    filter: LoFiFilter,
+    highpass: biquad::DirectForm1<f32>,
+    lowpass: biquad::DirectForm1<f32>,
+    bit_depth: f32,
+    dither: bool,
+    target_rate: f32,
+    hold_phase: f32,
+    held: f32,
+    crackle: f32,
+    crackle_level: f32,
+    crackle_decay: f32,
+    noise_state: u32,
+}
+
+impl LoFi {
+    pub fn new(sample_rate: f32) -> Self {
+        let filter = LoFiFilter::Telephone;
+        let (low, high) = filter.band();
+        let mut lofi = Self {
+            sample_rate,
+            filter,
+            highpass: biquad::DirectForm1::new(Self::filter_coeffs(
+                biquad::Type::HighPass,
+                low,
+                sample_rate,
+            )),
+            lowpass: biquad::DirectForm1::new(Self::filter_coeffs(
+                biquad::Type::LowPass,
+                high,
+                sample_rate,
+            )),
+            bit_depth: 8.0,
+            dither: false,
+            target_rate: 11025.0,
+            hold_phase: 0.0,
+            held: 0.0,
+            crackle: 0.0,
+            crackle_level: 0.0,
+            crackle_decay: 0.0,
+            noise_state: 0x6b43_a9b5,
+        };
+        lofi.update_coefficients();
+        lofi
+    }
+
+    fn filter_coeffs(
+        filter_type: biquad::Type<f32>,
+        frequency: f32,
+        sample_rate: f32,
+    ) -> biquad::Coefficients<f32> {
+        biquad::Coefficients::<f32>::from_params(
+            filter_type,
+            sample_rate.hz(),
+            frequency.min(sample_rate * 0.45).hz(),
+            biquad::Q_BUTTERWORTH_F32,
+        )
+        .unwrap()
+    }
+
+    fn update_coefficients(&mut self) {
+        let (low, high) = self.filter.band();
+        self.highpass.update_coefficients(Self::filter_coeffs(
+            biquad::Type::HighPass,
+            low,
+            self.sample_rate,
+        ));
+        self.lowpass.update_coefficients(Self::filter_coeffs(
+            biquad::Type::LowPass,
+            high,
+            self.sample_rate,
+        ));
+        self.crackle_decay = (-1.0 / (CRACKLE_DECAY_TIME * self.sample_rate)).exp();
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        if self.sample_rate != sample_rate {
+            self.sample_rate = sample_rate;
+            self.update_coefficients();
+        }
+    }
+
+    pub fn set_filter(&mut self, filter: LoFiFilter) {
+        if self.filter != filter {
+            self.filter = filter;
+            self.update_coefficients();
+        }
+    }
+
+    /// Sets the bit depth. Fractional depths are allowed so automation sweeps smoothly.
+    pub fn set_bit_depth(&mut self, bit_depth: f32, dither: bool) {
+        self.bit_depth = bit_depth.clamp(1.0, 24.0);
+        self.dither = dither;
+    }
+
+    /// Sets the rate the signal is held at, in Hz.
+    pub fn set_target_rate(&mut self, target_rate: f32) {
+        self.target_rate = target_rate.max(1.0);
+    }
+
+    /// Sets the crackle amount from 0.0 to 1.0.
+    pub fn set_crackle(&mut self, crackle: f32) {
+        self.crackle = crackle.clamp(0.0, 1.0);
+    }
+
+    // Cheap xorshift noise in the range [0, 1]
+    fn next_random(&mut self) -> f32 {
+        self.noise_state ^= self.noise_state << 13;
+        self.noise_state ^= self.noise_state >> 17;
+        self.noise_state ^= self.noise_state << 5;
+        self.noise_state as f32 / u32::MAX as f32
+    }
+
+    fn quantize(&mut self, input: f32) -> f32 {
+        let steps = 2.0_f32.powf(self.bit_depth - 1.0);
+        let dither = if self.dither {
+            // TPDF dither spanning one step either way
+            self.next_random() - self.next_random()
+        } else {
+            0.0
+        };
+
+        ((input * steps + dither).round() / steps).clamp(-1.0, 1.0)
+    }
+
+    pub fn process(&mut self, input: f32) -> f32 {
+        let band = if self.filter == LoFiFilter::Off {
+            input
+        } else {
+            self.lowpass.run(self.highpass.run(input))
+        };
+
+        // Sample and hold at the target rate
+        self.hold_phase += self.target_rate / self.sample_rate;
+        if self.hold_phase >= 1.0 {
+            self.hold_phase -= self.hold_phase.floor();
+            self.held = self.quantize(band);
+        }
+        let mut output = self.held;
+
+        if self.crackle > 0.0 {
+            let density = self.crackle * CRACKLE_MAX_DENSITY / self.sample_rate;
+            if self.next_random() < density {
+                // A click with a random polarity and size
+                let size = self.next_random();
+                let polarity = if self.next_random() < 0.5 { -1.0 } else { 1.0 };
+                self.crackle_level = polarity * size * size * 0.3 * self.crackle;
+            }
+            output += self.crackle_level * (self.next_random() * 2.0 - 1.0).abs();
+            self.crackle_level *= self.crackle_decay;
+        }
+
+        output
+    }
+
+    pub fn reset(&mut self) {
+        self.highpass.reset_state();
+        self.lowpass.reset_state();
+        self.hold_phase = 0.0;
+        self.held = 0.0;
+        self.crackle_level = 0.0;
+    }
+}
@@ -0,0 +1,419 @@
+use nih_plug::buffer::Buffer;
+use nih_plug::prelude::Enum;
+use std::f64::consts::PI;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+// Everything is measured in 100 ms steps, momentary spans 4 and short-term 30 of them
+const STEP_TIME: f64 = 0.1;
+const MOMENTARY_STEPS: usize = 4;
+const SHORT_TERM_STEPS: usize = 30;
+
+// Gates from ITU-R BS.1770-4 and EBU Tech 3342
+const ABSOLUTE_GATE_LUFS: f64 = -70.0;
+const INTEGRATED_RELATIVE_GATE_LU: f64 = -10.0;
+const RANGE_RELATIVE_GATE_LU: f64 = -20.0;
+const RANGE_LOW_PERCENTILE: f64 = 0.10;
+const RANGE_HIGH_PERCENTILE: f64 = 0.95;
+
+// Gated blocks are collected in 0.1 LU wide bins between the absolute gate and +10 LUFS,
+// which keeps memory bounded no matter how long the measurement runs
+const HISTOGRAM_MIN_LUFS: f64 = ABSOLUTE_GATE_LUFS;
+const HISTOGRAM_BIN_LU: f64 = 0.1;
+const HISTOGRAM_BINS: usize = 800;
+
+// Reported while there's nothing to measure yet
+pub const LOUDNESS_SILENCE: f32 = f32::NEG_INFINITY;
+
+#[derive(Copy, Clone, Enum, PartialEq)]
+pub enum LoudnessTarget {
+    #[name = "-14 LUFS"]
+    Streaming,
+    #[name = "-16 LUFS"]
+    Podcast,
+    #[name = "-23 LUFS"]
+    Broadcast,
+}
+
+impl LoudnessTarget {
+    pub fn lufs(&self) -> f32 {
+        match self {
+            LoudnessTarget::Streaming => -14.0,
+            LoudnessTarget::Podcast => -16.0,
+            LoudnessTarget::Broadcast => -23.0,
+        }
+    }
+}
+
+/// Loudness readings in LUFS (LU for the range), published by the audio thread for the
+/// editor and for anything else in the plugin that wants to react to loudness.
+pub struct LoudnessMeter {
+    momentary: AtomicU32,
+    short_term: AtomicU32,
+    integrated: AtomicU32,
+    range: AtomicU32,
+    reset_requested: AtomicBool,
+}
+
+impl LoudnessMeter {
+    pub fn new() -> Self {
+        Self {
+            momentary: AtomicU32::new(LOUDNESS_SILENCE.to_bits()),
+            short_term: AtomicU32::new(LOUDNESS_SILENCE.to_bits()),
+            integrated: AtomicU32::new(LOUDNESS_SILENCE.to_bits()),
+            range: AtomicU32::new(0.0f32.to_bits()),
+            reset_requested: AtomicBool::new(false),
+        }
+    }
+
+    pub fn momentary(&self) -> f32 {
+        f32::from_bits(self.momentary.load(Ordering::Relaxed))
+    }
+
+    pub fn short_term(&self) -> f32 {
+        f32::from_bits(self.short_term.load(Ordering::Relaxed))
+    }
+
+    pub fn integrated(&self) -> f32 {
+        f32::from_bits(self.integrated.load(Ordering::Relaxed))
+    }
+
+    pub fn range(&self) -> f32 {
+        f32::from_bits(self.range.load(Ordering::Relaxed))
+    }
+
+    /// Asks the audio thread to start the integrated and range measurements over.
+    pub fn request_reset(&self) {
+        self.reset_requested.store(true, Ordering::Relaxed);
+    }
+
+    /// Drops every reading, for when the measurement starts over from silence.
+    pub fn clear(&self) {
+        Self::store(&self.momentary, f64::NEG_INFINITY);
+        Self::store(&self.short_term, f64::NEG_INFINITY);
+        Self::store(&self.integrated, f64::NEG_INFINITY);
+        Self::store(&self.range, 0.0);
+    }
+
+    fn store(value: &AtomicU32, loudness: f64) {
+        value.store((loudness as f32).to_bits(), Ordering::Relaxed);
+    }
+}
+
+impl Default for LoudnessMeter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn power_to_lufs(power: f64) -> f64 {
+    if power > 0.0 {
+        -0.691 + 10.0 * power.log10()
+    } else {
+        f64::NEG_INFINITY
+    }
+}
+
+fn lufs_to_power(lufs: f64) -> f64 {
+    10.0_f64.powf((lufs + 0.691) / 10.0)
+}
+
+/// Loudness values of gated blocks, binned so percentiles and means can be taken without
+/// storing every block.
+struct LoudnessHistogram {
+    counts: Vec<u32>,
+    // Summed power per bin, so means aren't limited to the bin resolution
+    powers: Vec<f64>,
+}
+
+impl LoudnessHistogram {
+    fn new() -> Self {
+        Self {
+            counts: vec![0; HISTOGRAM_BINS],
+            powers: vec![0.0; HISTOGRAM_BINS],
+        }
+    }
+
+    fn bin(lufs: f64) -> Option<usize> {
+        if lufs <= HISTOGRAM_MIN_LUFS {
+            return None;
+        }
+        let bin = ((lufs - HISTOGRAM_MIN_LUFS) / HISTOGRAM_BIN_LU) as usize;
+        Some(bin.min(HISTOGRAM_BINS - 1))
+    }
+
+    fn bin_lufs(bin: usize) -> f64 {
+        HISTOGRAM_MIN_LUFS + (bin as f64 + 0.5) * HISTOGRAM_BIN_LU
+    }
+
+    fn add(&mut self, power: f64) {
+        if let Some(bin) = Self::bin(power_to_lufs(power)) {
+            self.counts[bin] += 1;
+            self.powers[bin] += power;
+        }
+    }
+
+    // The absolute gate is implied by the histogram's lower edge
+    fn mean_power_above(&self, gate_lufs: f64) -> Option<f64> {
+        let first = Self::bin(gate_lufs).unwrap_or(0);
+        let count: u64 = self.counts[first..].iter().map(|&count| count as u64).sum();
+        if count == 0 {
+            return None;
+        }
+        Some(self.powers[first..].iter().sum::<f64>() / count as f64)
+    }
+
+    fn percentile_above(&self, gate_lufs: f64, percentile: f64) -> Option<f64> {
+        let first = Self::bin(gate_lufs).unwrap_or(0);
+        let count: u64 = self.counts[first..].iter().map(|&count| count as u64).sum();
+        if count == 0 {
+            return None;
+        }
+
+        let target = (percentile * (count - 1) as f64).round() as u64;
+        let mut seen = 0;
+        for (bin, &bin_count) in self.counts.iter().enumerate().skip(first) {
+            seen += bin_count as u64;
+            if seen > target {
+                return Some(Self::bin_lufs(bin));
+            }
+        }
+        None
+    }
+
+    fn clear(&mut self) {
+        self.counts.iter_mut().for_each(|count| *count = 0);
+        self.powers.iter_mut().for_each(|power| *power = 0.0);
+    }
+}
+
+/// ITU-R BS.1770 / EBU R128 loudness measurement running on the audio thread. The signal is
+/// K-weighted per channel, summed with the surround channel weights and integrated in
+/// 100 ms steps into momentary, short-term, gated integrated loudness and loudness range.
+pub struct LoudnessAnalyzer {
+    sample_rate: f32,
+    shelf: Vec<biquad::DirectForm2Transposed<f64>>,
+    highpass: Vec<biquad::DirectForm2Transposed<f64>>,
+    weights: Vec<f64>,
+    step_samples: usize,
+    step_count: usize,
+    step_power: f64,
+    // The last `SHORT_TERM_STEPS` step powers, newest at `steps_pos - 1`
+    steps: [f64; SHORT_TERM_STEPS],
+    steps_pos: usize,
+    steps_filled: usize,
+    integrated: LoudnessHistogram,
+    range: LoudnessHistogram,
+}
+
+impl LoudnessAnalyzer {
+    pub fn new(sample_rate: f32, num_channels: usize) -> Self {
+        let mut analyzer = Self {
+            sample_rate,
+            shelf: Vec::new(),
+            highpass: Vec::new(),
+            weights: Vec::new(),
+            step_samples: 1,
+            step_count: 0,
+            step_power: 0.0,
+            steps: [0.0; SHORT_TERM_STEPS],
+            steps_pos: 0,
+            steps_filled: 0,
+            integrated: LoudnessHistogram::new(),
+            range: LoudnessHistogram::new(),
+        };
+        analyzer.set_num_channels(num_channels);
+        analyzer.set_sample_rate(sample_rate);
+        analyzer
+    }
+
+    // The K-weighting pre-filter and RLB highpass, with the coefficients derived for any
+    // sample rate as described in BS.1770
+    fn shelf_coeffs(sample_rate: f64) -> biquad::Coefficients<f64> {
+        let f0 = 1681.974450955533;
+        let gain_db = 3.999843853973347;
+        let q = 0.7071752369554196;
+
+        let k = (PI * f0 / sample_rate).tan();
+        let vh = 10.0_f64.powf(gain_db / 20.0);
+        let vb = vh.powf(0.4996667741545416);
+        let a0 = 1.0 + k / q + k * k;
+        biquad::Coefficients {
+            a1: 2.0 * (k * k - 1.0) / a0,
+            a2: (1.0 - k / q + k * k) / a0,
+            b0: (vh + vb * k / q + k * k) / a0,
+            b1: 2.0 * (k * k - vh) / a0,
+            b2: (vh - vb * k / q + k * k) / a0,
+        }
+    }
+
+    fn highpass_coeffs(sample_rate: f64) -> biquad::Coefficients<f64> {
+        let f0 = 38.13547087602444;
+        let q = 0.5003270373238773;
+
+        let k = (PI * f0 / sample_rate).tan();
+        let a0 = 1.0 + k / q + k * k;
+        biquad::Coefficients {
+            a1: 2.0 * (k * k - 1.0) / a0,
+            a2: (1.0 - k / q + k * k) / a0,
+            b0: 1.0,
+            b1: -2.0,
+            b2: 1.0,
+        }
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+        let shelf = Self::shelf_coeffs(sample_rate as f64);
+        let highpass = Self::highpass_coeffs(sample_rate as f64);
+        for filter in self.shelf.iter_mut() {
+            *filter = biquad::DirectForm2Transposed::<f64>::new(shelf);
+        }
+        for filter in self.highpass.iter_mut() {
+            *filter = biquad::DirectForm2Transposed::<f64>::new(highpass);
+        }
+        self.step_samples = ((STEP_TIME * sample_rate as f64).round() as usize).max(1);
+        self.reset();
+    }
+
+    pub fn set_num_channels(&mut self, num_channels: usize) {
+        let shelf = Self::shelf_coeffs(self.sample_rate as f64);
+        let highpass = Self::highpass_coeffs(self.sample_rate as f64);
+        self.shelf = (0..num_channels)
+            .map(|_| biquad::DirectForm2Transposed::<f64>::new(shelf))
+            .collect();
+        self.highpass = (0..num_channels)
+            .map(|_| biquad::DirectForm2Transposed::<f64>::new(highpass))
+            .collect();
+
+        // Surround channels count a bit more and the LFE channel doesn't count at all, this
+        // assumes the usual L R C LFE Ls Rs (Lb Rb) ordering
+        self.weights = (0..num_channels)
+            .map(|channel| match (num_channels, channel) {
+                (4, 2..=3) => 1.41,
+                (6 | 8, 3) => 0.0,
+                (6 | 8, 4..) => 1.41,
+                _ => 1.0,
+            })
+            .collect();
+    }
+
+    /// Starts the integrated loudness and loudness range measurements over.
+    pub fn reset(&mut self) {
+        self.shelf.iter_mut().for_each(|filter| filter.reset_state());
+        self.highpass.iter_mut().for_each(|filter| filter.reset_state());
+        self.step_count = 0;
+        self.step_power = 0.0;
+        self.steps = [0.0; SHORT_TERM_STEPS];
+        self.steps_pos = 0;
+        self.steps_filled = 0;
+        self.integrated.clear();
+        self.range.clear();
+    }
+
+    // Mean power over the last `num_steps` steps, once that many have been measured
+    fn window_power(&self, num_steps: usize) -> Option<f64> {
+        if self.steps_filled < num_steps {
+            return None;
+        }
+        let sum: f64 = (1..=num_steps)
+            .map(|age| self.steps[(self.steps_pos + SHORT_TERM_STEPS - age) % SHORT_TERM_STEPS])
+            .sum();
+        Some(sum / num_steps as f64)
+    }
+
+    fn finish_step(&mut self, meter: &LoudnessMeter) {
+        self.steps[self.steps_pos] = self.step_power / self.step_samples as f64;
+        self.steps_pos = (self.steps_pos + 1) % SHORT_TERM_STEPS;
+        self.steps_filled = (self.steps_filled + 1).min(SHORT_TERM_STEPS);
+        self.step_count = 0;
+        self.step_power = 0.0;
+
+        if let Some(momentary) = self.window_power(MOMENTARY_STEPS) {
+            // Gating blocks are the momentary windows, 400 ms with 75% overlap
+            self.integrated.add(momentary);
+            LoudnessMeter::store(&meter.momentary, power_to_lufs(momentary));
+        }
+        if let Some(short_term) = self.window_power(SHORT_TERM_STEPS) {
+            self.range.add(short_term);
+            LoudnessMeter::store(&meter.short_term, power_to_lufs(short_term));
+        }
+
+        let integrated = self.integrated.mean_power_above(ABSOLUTE_GATE_LUFS).and_then(|power| {
+            let gate = power_to_lufs(power) + INTEGRATED_RELATIVE_GATE_LU;
+            self.integrated.mean_power_above(gate)
+        });
+        LoudnessMeter::store(
+            &meter.integrated,
+            integrated.map(power_to_lufs).unwrap_or(f64::NEG_INFINITY),
+        );
+
+        let range = self.range.mean_power_above(ABSOLUTE_GATE_LUFS).and_then(|power| {
+            let gate = power_to_lufs(power) + RANGE_RELATIVE_GATE_LU;
+            let low = self.range.percentile_above(gate, RANGE_LOW_PERCENTILE)?;
+            let high = self.range.percentile_above(gate, RANGE_HIGH_PERCENTILE)?;
+            Some(high - low)
+        });
+        LoudnessMeter::store(&meter.range, range.unwrap_or(0.0));
+    }
+
+    pub fn process(&mut self, buffer: &Buffer, meter: &LoudnessMeter) {
+        if meter.reset_requested.swap(false, Ordering::Relaxed) {
+            self.reset();
+            LoudnessMeter::store(&meter.integrated, f64::NEG_INFINITY);
+            LoudnessMeter::store(&meter.range, 0.0);
+        }
+
+        self.process_channels(buffer.as_slice_immutable(), buffer.samples(), meter);
+    }
+
+    fn process_channels<C: AsRef<[f32]>>(
+        &mut self,
+        channels: &[C],
+        num_samples: usize,
+        meter: &LoudnessMeter,
+    ) {
+        let num_channels = channels.len().min(self.weights.len());
+        for sample_idx in 0..num_samples {
+            for channel in 0..num_channels {
+                let sample = channels[channel].as_ref()[sample_idx] as f64;
+                let weighted = self.highpass[channel].run(self.shelf[channel].run(sample));
+                self.step_power += self.weights[channel] * weighted * weighted;
+            }
+
+            self.step_count += 1;
+            if self.step_count >= self.step_samples {
+                self.finish_step(meter);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::PI;
+
+    const SAMPLE_RATE: f32 = 48000.0;
+
+    // EBU Tech 3341 test case 1: a stereo 1 kHz sine at -23 dBFS reads -23 LUFS
+    #[test]
+    fn sine_at_minus_23_dbfs_reads_minus_23_lufs() {
+        let amplitude = 10.0f32.powf(-23.0 / 20.0);
+        let num_samples = 3 * SAMPLE_RATE as usize;
+        let sine: Vec<f32> = (0..num_samples)
+            .map(|n| amplitude * (2.0 * PI * 1000.0 * n as f32 / SAMPLE_RATE).sin())
+            .collect();
+
+        let mut analyzer = LoudnessAnalyzer::new(SAMPLE_RATE, 2);
+        let meter = LoudnessMeter::new();
+        analyzer.process_channels(&[sine.clone(), sine], num_samples, &meter);
+
+        for (name, lufs) in [
+            ("momentary", meter.momentary()),
+            ("short-term", meter.short_term()),
+            ("integrated", meter.integrated()),
+        ] {
+            assert!((lufs + 23.0).abs() < 0.1, "{name} loudness reads {lufs} LUFS");
+        }
+    }
+}
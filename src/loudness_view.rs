@@ -0,0 +1,109 @@
+use nih_plug::prelude::*;
+use nih_plug_vizia::vizia::prelude::*;
+use nih_plug_vizia::vizia::vg;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::device::KVPChannelPluginParams;
+use crate::loudness::LoudnessMeter;
+use crate::theme::ThemeState;
+use crate::ui::{draw_background, start_refresh_timer, vg_color};
+
+// Range of the loudness scale
+const LOUDNESS_MIN_LUFS: f32 = -40.0;
+const LOUDNESS_MAX_LUFS: f32 = 0.0;
+// The integrated reading counts as on target within this many LU
+const TARGET_TOLERANCE_LU: f32 = 1.0;
+const LOUDNESS_REFRESH: Duration = Duration::from_millis(100);
+
+const ON_TARGET_COLOR: Color = Color::rgb(120, 190, 90);
+
+/// Formats a loudness reading for the editor's readouts.
+pub(crate) fn format_lufs(lufs: f32) -> String {
+    if lufs.is_finite() {
+        format!("{lufs:.1}")
+    } else {
+        String::from("-inf")
+    }
+}
+
+/// Momentary, short-term and integrated loudness bars with a marker at the selected
+/// loudness target. The integrated bar turns green once it's close to the target.
+pub struct LoudnessView {
+    meter: Arc<LoudnessMeter>,
+    params: Arc<KVPChannelPluginParams>,
+    theme: Arc<ThemeState>,
+}
+
+impl LoudnessView {
+    pub fn new(
+        cx: &mut Context,
+        meter: Arc<LoudnessMeter>,
+        params: Arc<KVPChannelPluginParams>,
+        theme: Arc<ThemeState>,
+    ) -> Handle<Self> {
+        Self {
+            meter,
+            params,
+            theme,
+        }
+        .build(cx, |cx| start_refresh_timer(cx, LOUDNESS_REFRESH))
+    }
+
+    fn lufs_to_position(lufs: f32) -> f32 {
+        if !lufs.is_finite() {
+            return 0.0;
+        }
+        ((lufs - LOUDNESS_MIN_LUFS) / (LOUDNESS_MAX_LUFS - LOUDNESS_MIN_LUFS)).clamp(0.0, 1.0)
+    }
+}
+
+impl View for LoudnessView {
+    fn element(&self) -> Option<&'static str> {
+        Some("loudness-meter")
+    }
+
+    fn draw(&self, cx: &mut DrawContext, canvas: &mut Canvas) {
+        let bounds = cx.bounds();
+        if bounds.w == 0.0 || bounds.h == 0.0 {
+            return;
+        }
+        let palette = self.theme.palette();
+
+        draw_background(canvas, bounds, palette);
+
+        let target = self.params.loudness_target.value().lufs();
+        let integrated = self.meter.integrated();
+        let integrated_color = if (integrated - target).abs() <= TARGET_TOLERANCE_LU {
+            ON_TARGET_COLOR
+        } else {
+            palette.accent
+        };
+
+        let readings = [
+            (self.meter.momentary(), palette.highlight),
+            (self.meter.short_term(), palette.highlight),
+            (integrated, integrated_color),
+        ];
+        let bar_width = bounds.w - 2.0;
+        let bar_height = bounds.h / readings.len() as f32;
+        for (idx, (lufs, color)) in readings.into_iter().enumerate() {
+            let mut bar = vg::Path::new();
+            bar.rect(
+                bounds.x + 1.0,
+                bounds.y + idx as f32 * bar_height + 1.0,
+                bar_width * Self::lufs_to_position(lufs),
+                (bar_height - 2.0).max(1.0),
+            );
+            canvas.fill_path(&bar, &vg::Paint::color(vg_color(color)));
+        }
+
+        let target_x = bounds.x + 1.0 + bar_width * Self::lufs_to_position(target);
+        let mut marker = vg::Path::new();
+        marker.move_to(target_x, bounds.y);
+        marker.line_to(target_x, bounds.y + bounds.h);
+        let mut paint = vg::Paint::color(vg_color(palette.text));
+        paint.set_line_width(2.0);
+        canvas.stroke_path(&marker, &paint);
+    }
+}
@@ -0,0 +1,320 @@
+use nih_plug::buffer::Buffer;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use crate::analyzer::SpectrumTap;
+use crate::loudness::LoudnessMeter;
+
+// The most channels any supported layout has
+pub const METER_MAX_CHANNELS: usize = 8;
+
+// Peak falloff and RMS integration times
+const PEAK_RELEASE_TIME: f32 = 0.3;
+const RMS_TIME: f32 = 0.3;
+// How long the held peak stays put before it follows the peak down again
+const PEAK_HOLD_TIME: f32 = 1.5;
+
+/// The readings of a single channel. Only the audio thread writes these, so a plain load
+/// followed by a store is enough. Levels are linear gains stored as `f32` bits.
+struct ChannelLevel {
+    peak: AtomicU32,
+    hold: AtomicU32,
+    hold_age: AtomicU32,
+    mean_square: AtomicU32,
+    clipped: AtomicBool,
+}
+
+impl ChannelLevel {
+    fn new() -> Self {
+        Self {
+            peak: AtomicU32::new(0),
+            hold: AtomicU32::new(0),
+            hold_age: AtomicU32::new(0),
+            mean_square: AtomicU32::new(0),
+            clipped: AtomicBool::new(false),
+        }
+    }
+
+    fn load(value: &AtomicU32) -> f32 {
+        f32::from_bits(value.load(Ordering::Relaxed))
+    }
+
+    fn store(value: &AtomicU32, level: f32) {
+        value.store(level.to_bits(), Ordering::Relaxed);
+    }
+}
+
+/// Peak, peak hold, RMS and clip readings for one point in the signal chain, published
+/// from `process()` and read by the editor.
+pub struct LevelMeter {
+    channels: [ChannelLevel; METER_MAX_CHANNELS],
+    num_channels: AtomicUsize,
+}
+
+impl LevelMeter {
+    pub fn new() -> Self {
+        Self {
+            channels: std::array::from_fn(|_| ChannelLevel::new()),
+            num_channels: AtomicUsize::new(0),
+        }
+    }
+
+    /// Measures a block of audio. Called from the audio thread only.
+    pub fn measure(&self, buffer: &Buffer, sample_rate: f32) {
+        let channels = buffer.as_slice_immutable();
+        let num_samples = buffer.samples();
+        if num_samples == 0 {
+            return;
+        }
+
+        let num_channels = channels.len().min(METER_MAX_CHANNELS);
+        self.num_channels.store(num_channels, Ordering::Relaxed);
+
+        // Per block versions of the one pole coefficients
+        let block_time = num_samples as f32 / sample_rate;
+        let peak_decay = (-block_time / PEAK_RELEASE_TIME).exp();
+        let rms_decay = (-block_time / RMS_TIME).exp();
+        let hold_samples = (PEAK_HOLD_TIME * sample_rate) as u32;
+
+        for (samples, level) in channels.iter().zip(self.channels.iter()) {
+            let block_peak = samples.iter().fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+            let block_mean_square =
+                samples.iter().map(|sample| sample * sample).sum::<f32>() / num_samples as f32;
+
+            let peak = block_peak.max(ChannelLevel::load(&level.peak) * peak_decay);
+            ChannelLevel::store(&level.peak, peak);
+
+            let hold_age = level.hold_age.load(Ordering::Relaxed) + num_samples as u32;
+            if block_peak >= ChannelLevel::load(&level.hold) || hold_age > hold_samples {
+                ChannelLevel::store(&level.hold, peak);
+                level.hold_age.store(0, Ordering::Relaxed);
+            } else {
+                level.hold_age.store(hold_age, Ordering::Relaxed);
+            }
+
+            let mean_square = ChannelLevel::load(&level.mean_square);
+            ChannelLevel::store(
+                &level.mean_square,
+                block_mean_square + (mean_square - block_mean_square) * rms_decay,
+            );
+
+            if block_peak >= 1.0 {
+                level.clipped.store(true, Ordering::Relaxed);
+            }
+        }
+    }
+
+    pub fn num_channels(&self) -> usize {
+        self.num_channels.load(Ordering::Relaxed)
+    }
+
+    pub fn peak(&self, channel: usize) -> f32 {
+        ChannelLevel::load(&self.channels[channel].peak)
+    }
+
+    pub fn hold(&self, channel: usize) -> f32 {
+        ChannelLevel::load(&self.channels[channel].hold)
+    }
+
+    pub fn rms(&self, channel: usize) -> f32 {
+        ChannelLevel::load(&self.channels[channel].mean_square).sqrt()
+    }
+
+    pub fn clipped(&self, channel: usize) -> bool {
+        self.channels[channel].clipped.load(Ordering::Relaxed)
+    }
+
+    /// Clears the clip indicators of every channel, called from the editor.
+    pub fn reset_clip(&self) {
+        for level in self.channels.iter() {
+            level.clipped.store(false, Ordering::Relaxed);
+        }
+    }
+}
+
+impl Default for LevelMeter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Every metering point in the chain, shared between the plugin and its editor.
+#[derive(Default)]
+pub struct Meters {
+    pub input: Arc<LevelMeter>,
+    pub post_eq: Arc<LevelMeter>,
+    pub post_compressor: Arc<LevelMeter>,
+    pub output: Arc<LevelMeter>,
+    pub gain_reduction: Arc<GainReductionMeter>,
+    pub loudness: Arc<LoudnessMeter>,
+    // Signal before and after the EQ for the spectrum analyzer
+    pub pre_eq_spectrum: Arc<SpectrumTap>,
+    pub post_eq_spectrum: Arc<SpectrumTap>,
+    // The editor needs this to draw filter responses, stored as `f32` bits
+    sample_rate: AtomicU32,
+}
+
+impl Meters {
+    /// The sample rate the plugin is running at, or 44.1 kHz before it has been initialized.
+    pub fn sample_rate(&self) -> f32 {
+        match ChannelLevel::load(&self.sample_rate) {
+            sample_rate if sample_rate > 0.0 => sample_rate,
+            _ => 44100.0,
+        }
+    }
+
+    pub fn set_sample_rate(&self, sample_rate: f32) {
+        ChannelLevel::store(&self.sample_rate, sample_rate);
+    }
+}
+
+// Columns in the gain reduction history and the time they span together
+pub const GR_HISTORY_LEN: usize = 512;
+const GR_HISTORY_TIME: f32 = 6.0;
+
+/// Current gain reduction per channel plus a scrolling history of the signal level and the
+/// gain reduction, so the editor can show how the compressor reacts over a phrase. Gain
+/// reduction is stored as a positive number of dB.
+pub struct GainReductionMeter {
+    channels: [AtomicU32; METER_MAX_CHANNELS],
+    num_channels: AtomicUsize,
+    // The loudest detector level of the last block in dB, along with its gain reduction
+    detector: AtomicU32,
+    detector_gain_reduction: AtomicU32,
+    history_level: [AtomicU32; GR_HISTORY_LEN],
+    history_gain_reduction: [AtomicU32; GR_HISTORY_LEN],
+    // The column that will be written next, i.e. one past the newest column
+    history_pos: AtomicUsize,
+}
+
+impl GainReductionMeter {
+    pub fn new() -> Self {
+        Self {
+            channels: std::array::from_fn(|_| AtomicU32::new(0)),
+            num_channels: AtomicUsize::new(0),
+            detector: AtomicU32::new((-120.0f32).to_bits()),
+            detector_gain_reduction: AtomicU32::new(0),
+            history_level: std::array::from_fn(|_| AtomicU32::new(0)),
+            history_gain_reduction: std::array::from_fn(|_| AtomicU32::new(0)),
+            history_pos: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn num_channels(&self) -> usize {
+        self.num_channels.load(Ordering::Relaxed)
+    }
+
+    pub fn gain_reduction(&self, channel: usize) -> f32 {
+        ChannelLevel::load(&self.channels[channel])
+    }
+
+    /// The `(detector level, gain reduction)` in dB of the loudest channel in the last block.
+    pub fn detector(&self) -> (f32, f32) {
+        (
+            ChannelLevel::load(&self.detector),
+            ChannelLevel::load(&self.detector_gain_reduction),
+        )
+    }
+
+    /// Returns the `(level, gain reduction)` of a history column, where 0 is the oldest and
+    /// `GR_HISTORY_LEN - 1` the newest column.
+    pub fn history(&self, column: usize) -> (f32, f32) {
+        let pos = (self.history_pos.load(Ordering::Acquire) + column) % GR_HISTORY_LEN;
+        (
+            ChannelLevel::load(&self.history_level[pos]),
+            ChannelLevel::load(&self.history_gain_reduction[pos]),
+        )
+    }
+}
+
+impl Default for GainReductionMeter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The audio thread's side of a `GainReductionMeter`. It collects every sample into the
+/// current history column and publishes the column once it's full.
+pub struct GainReductionRecorder {
+    meter: Arc<GainReductionMeter>,
+    column_samples: usize,
+    count: usize,
+    level: f32,
+    gain_reduction: f32,
+    block_gain_reduction: [f32; METER_MAX_CHANNELS],
+    block_detector: (f32, f32),
+}
+
+impl GainReductionRecorder {
+    pub fn new(meter: Arc<GainReductionMeter>, sample_rate: f32) -> Self {
+        let mut recorder = Self {
+            meter,
+            column_samples: 1,
+            count: 0,
+            level: 0.0,
+            gain_reduction: 0.0,
+            block_gain_reduction: [0.0; METER_MAX_CHANNELS],
+            block_detector: (f32::NEG_INFINITY, 0.0),
+        };
+        recorder.set_sample_rate(sample_rate);
+        recorder
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.column_samples =
+            ((GR_HISTORY_TIME * sample_rate) / GR_HISTORY_LEN as f32).max(1.0) as usize;
+    }
+
+    /// Records one channel's output sample, detector level and gain reduction in dB.
+    pub fn record(&mut self, channel: usize, output: f32, detector: f32, gain_reduction: f32) {
+        if detector > self.block_detector.0 {
+            self.block_detector = (detector, gain_reduction);
+        }
+        self.level = self.level.max(output.abs());
+        self.gain_reduction = self.gain_reduction.max(gain_reduction);
+        if let Some(block_gain_reduction) = self.block_gain_reduction.get_mut(channel) {
+            *block_gain_reduction = block_gain_reduction.max(gain_reduction);
+        }
+    }
+
+    /// Advances the history by one sample, called after all channels of a sample have been
+    /// recorded.
+    pub fn advance(&mut self) {
+        self.count += 1;
+        if self.count < self.column_samples {
+            return;
+        }
+
+        let pos = self.meter.history_pos.load(Ordering::Relaxed);
+        ChannelLevel::store(&self.meter.history_level[pos], self.level);
+        ChannelLevel::store(&self.meter.history_gain_reduction[pos], self.gain_reduction);
+        self.meter
+            .history_pos
+            .store((pos + 1) % GR_HISTORY_LEN, Ordering::Release);
+
+        self.count = 0;
+        self.level = 0.0;
+        self.gain_reduction = 0.0;
+    }
+
+    /// Publishes the largest gain reduction of the block for every channel, and the loudest
+    /// detector level.
+    pub fn finish_block(&mut self, num_channels: usize) {
+        let (detector, gain_reduction) = self.block_detector;
+        if detector.is_finite() {
+            ChannelLevel::store(&self.meter.detector, detector);
+            ChannelLevel::store(&self.meter.detector_gain_reduction, gain_reduction);
+        }
+        self.block_detector = (f32::NEG_INFINITY, 0.0);
+
+        let num_channels = num_channels.min(METER_MAX_CHANNELS);
+        self.meter.num_channels.store(num_channels, Ordering::Relaxed);
+        for (channel, block_gain_reduction) in self.meter.channels[..num_channels]
+            .iter()
+            .zip(self.block_gain_reduction.iter_mut())
+        {
+            ChannelLevel::store(channel, *block_gain_reduction);
+            *block_gain_reduction = 0.0;
+        }
+    }
+}
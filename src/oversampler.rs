@@ -0,0 +1,455 @@
+use nih_plug::prelude::Enum;
+use std::f32::consts::PI;
+
+// The largest supported factor, used to size the per-sample scratch buffers
+const MAX_FACTOR: usize = 8;
+
+// Polyphase allpass coefficients for an 8th order steep half-band filter. The first chain
+// handles the even phase, the second one the odd phase.
+const ALLPASS_COEFFS_A: [f32; 4] = [
+    0.077_115_08,
+    0.482_070_62,
+    0.796_820_47,
+    0.941_251_43,
+];
+const ALLPASS_COEFFS_B: [f32; 4] = [
+    0.265_968_53,
+    0.665_104_15,
+    0.884_101_5,
+    0.982_005_4,
+];
+
+#[derive(Copy, Clone, Enum, PartialEq)]
+pub enum OversamplingFactor {
+    #[name = "Off"]
+    Off,
+    #[name = "2x"]
+    Times2,
+    #[name = "4x"]
+    Times4,
+    #[name = "8x"]
+    Times8,
+}
+
+impl OversamplingFactor {
+    pub fn num_stages(&self) -> usize {
+        match self {
+            OversamplingFactor::Off => 0,
+            OversamplingFactor::Times2 => 1,
+            OversamplingFactor::Times4 => 2,
+            OversamplingFactor::Times8 => 3,
+        }
+    }
+
+    pub fn factor(&self) -> usize {
+        1 << self.num_stages()
+    }
+}
+
+#[derive(Copy, Clone, Enum, PartialEq)]
+pub enum OversamplingPhase {
+    // Polyphase IIR allpass filters, only a couple of samples of latency but not phase linear
+    Minimum,
+    // Polyphase FIR filters, phase linear at the cost of more latency
+    Linear,
+}
+
+/// A 2x half-band FIR filter split into its two polyphase components. Only the taps at odd
+/// offsets from the center are nonzero, so the odd phase reduces to a pure delay.
+struct FirHalfBand {
+    // The even phase taps, h[2j]
+    taps: Vec<f32>,
+    even_history: Vec<f32>,
+    odd_history: Vec<f32>,
+    pos: usize,
+}
+
+impl FirHalfBand {
+    /// Creates a windowed-sinc half-band filter with `4 * half_len - 1` taps.
+    fn new(half_len: usize) -> Self {
+        let num_taps = 4 * half_len - 1;
+        let center = (num_taps - 1) as f32 / 2.0;
+
+        let mut taps: Vec<f32> = (0..2 * half_len)
+            .map(|j| {
+                let k = (2 * j) as f32;
+                let offset = (k - center) / 2.0;
+                let sinc = (PI * offset).sin() / (PI * offset);
+                // Blackman-Harris window for good stopband rejection
+                let phase = 2.0 * PI * k / (num_taps - 1) as f32;
+                let window = 0.35875 - 0.48829 * phase.cos() + 0.14128 * (2.0 * phase).cos()
+                    - 0.01168 * (3.0 * phase).cos();
+                0.5 * sinc * window
+            })
+            .collect();
+
+        // The even phase needs to sum to 0.5 for unity gain at DC, the center tap supplies the
+        // other half
+        let sum: f32 = taps.iter().sum();
+        taps.iter_mut().for_each(|tap| *tap *= 0.5 / sum);
+
+        Self {
+            taps,
+            even_history: vec![0.0; 2 * half_len],
+            odd_history: vec![0.0; half_len + 1],
+            pos: 0,
+        }
+    }
+
+    /// The filter's delay in samples at the higher of the two rates.
+    fn latency(&self) -> f32 {
+        (self.taps.len() - 1) as f32
+    }
+
+    fn push(history: &mut [f32], pos: usize, sample: f32) {
+        let len = history.len();
+        history[pos % len] = sample;
+    }
+
+    fn delayed(history: &[f32], pos: usize, delay: usize) -> f32 {
+        let len = history.len();
+        history[(pos + len - delay % len) % len]
+    }
+
+    fn convolve(&self) -> f32 {
+        self.taps
+            .iter()
+            .enumerate()
+            .map(|(j, tap)| tap * Self::delayed(&self.even_history, self.pos, j))
+            .sum()
+    }
+
+    fn upsample(&mut self, input: f32) -> [f32; 2] {
+        Self::push(&mut self.even_history, self.pos, input);
+        let half_len = self.odd_history.len() - 1;
+
+        let even = 2.0 * self.convolve();
+        let odd = Self::delayed(&self.even_history, self.pos, half_len - 1);
+        self.pos += 1;
+
+        [even, odd]
+    }
+
+    fn downsample(&mut self, input: [f32; 2]) -> f32 {
+        Self::push(&mut self.even_history, self.pos, input[0]);
+        Self::push(&mut self.odd_history, self.pos, input[1]);
+        let half_len = self.odd_history.len() - 1;
+
+        let output =
+            self.convolve() + 0.5 * Self::delayed(&self.odd_history, self.pos, half_len);
+        self.pos += 1;
+
+        output
+    }
+
+    fn reset(&mut self) {
+        self.even_history.iter_mut().for_each(|sample| *sample = 0.0);
+        self.odd_history.iter_mut().for_each(|sample| *sample = 0.0);
+        self.pos = 0;
+    }
+}
+
+/// A first order allpass section running at the lower of the two rates.
+struct AllpassSection {
+    coeff: f32,
+    x1: f32,
+    y1: f32,
+}
+
+impl AllpassSection {
+    fn new(coeff: f32) -> Self {
+        Self {
+            coeff,
+            x1: 0.0,
+            y1: 0.0,
+        }
+    }
+
+    fn process(&mut self, input: f32) -> f32 {
+        let output = self.coeff * (input - self.y1) + self.x1;
+        self.x1 = input;
+        self.y1 = output;
+        output
+    }
+
+    /// Group delay at DC in samples at the higher rate, where the section acts on z^-2.
+    fn latency(&self) -> f32 {
+        2.0 * (1.0 - self.coeff) / (1.0 + self.coeff)
+    }
+
+    fn reset(&mut self) {
+        self.x1 = 0.0;
+        self.y1 = 0.0;
+    }
+}
+
+/// A 2x half-band filter built from two parallel allpass chains.
+struct IirHalfBand {
+    chain_a: Vec<AllpassSection>,
+    chain_b: Vec<AllpassSection>,
+    prev_b: f32,
+}
+
+impl IirHalfBand {
+    fn new() -> Self {
+        Self {
+            chain_a: ALLPASS_COEFFS_A.iter().map(|&a| AllpassSection::new(a)).collect(),
+            chain_b: ALLPASS_COEFFS_B.iter().map(|&b| AllpassSection::new(b)).collect(),
+            prev_b: 0.0,
+        }
+    }
+
+    fn run_chain(chain: &mut [AllpassSection], input: f32) -> f32 {
+        chain
+            .iter_mut()
+            .fold(input, |sample, section| section.process(sample))
+    }
+
+    /// The filter's group delay at DC in samples at the higher of the two rates. The odd
+    /// branch carries an extra sample of delay and both branches are averaged.
+    fn latency(&self) -> f32 {
+        let chain_a: f32 = self.chain_a.iter().map(AllpassSection::latency).sum();
+        let chain_b: f32 = self.chain_b.iter().map(AllpassSection::latency).sum();
+        0.5 * (chain_a + 1.0 + chain_b)
+    }
+
+    fn upsample(&mut self, input: f32) -> [f32; 2] {
+        [
+            Self::run_chain(&mut self.chain_a, input),
+            Self::run_chain(&mut self.chain_b, input),
+        ]
+    }
+
+    fn downsample(&mut self, input: [f32; 2]) -> f32 {
+        let output = 0.5 * (Self::run_chain(&mut self.chain_a, input[0]) + self.prev_b);
+        self.prev_b = Self::run_chain(&mut self.chain_b, input[1]);
+        output
+    }
+
+    fn reset(&mut self) {
+        self.chain_a.iter_mut().for_each(AllpassSection::reset);
+        self.chain_b.iter_mut().for_each(AllpassSection::reset);
+        self.prev_b = 0.0;
+    }
+}
+
+enum HalfBand {
+    Linear(FirHalfBand),
+    Minimum(IirHalfBand),
+}
+
+impl HalfBand {
+    fn new(phase: OversamplingPhase, stage: usize) -> Self {
+        match phase {
+            // Later stages have a much wider transition band relative to their rate, so they
+            // get away with shorter filters
+            OversamplingPhase::Linear => HalfBand::Linear(FirHalfBand::new(16 >> stage)),
+            OversamplingPhase::Minimum => HalfBand::Minimum(IirHalfBand::new()),
+        }
+    }
+
+    fn latency(&self) -> f32 {
+        match self {
+            HalfBand::Linear(filter) => filter.latency(),
+            HalfBand::Minimum(filter) => filter.latency(),
+        }
+    }
+
+    fn upsample(&mut self, input: f32) -> [f32; 2] {
+        match self {
+            HalfBand::Linear(filter) => filter.upsample(input),
+            HalfBand::Minimum(filter) => filter.upsample(input),
+        }
+    }
+
+    fn downsample(&mut self, input: [f32; 2]) -> f32 {
+        match self {
+            HalfBand::Linear(filter) => filter.downsample(input),
+            HalfBand::Minimum(filter) => filter.downsample(input),
+        }
+    }
+
+    fn reset(&mut self) {
+        match self {
+            HalfBand::Linear(filter) => filter.reset(),
+            HalfBand::Minimum(filter) => filter.reset(),
+        }
+    }
+}
+
+struct Stage {
+    up: HalfBand,
+    down: HalfBand,
+}
+
+impl Stage {
+    fn new(phase: OversamplingPhase, stage: usize) -> Self {
+        Self {
+            up: HalfBand::new(phase, stage),
+            down: HalfBand::new(phase, stage),
+        }
+    }
+
+    fn reset(&mut self) {
+        self.up.reset();
+        self.down.reset();
+    }
+}
+
+/// Runs a nonlinear process at 2x, 4x or 8x the host sample rate using cascaded polyphase
+/// half-band filters. Each channel needs its own instance. The filters for every factor and
+/// phase are allocated up front, so switching between them is safe on the audio thread.
+pub struct Oversampler {
+    factor: OversamplingFactor,
+    phase: OversamplingPhase,
+    // All stages for both phases, the first `factor.num_stages()` of one of them are used
+    minimum_stages: Vec<Stage>,
+    linear_stages: Vec<Stage>,
+    // Extra delay at the oversampled rate that rounds the linear phase latency up to a whole
+    // number of samples at the host rate
+    padding: [f32; MAX_FACTOR],
+    padding_len: usize,
+    padding_pos: usize,
+    latency: u32,
+}
+
+impl Oversampler {
+    pub fn new(factor: OversamplingFactor, phase: OversamplingPhase) -> Self {
+        let max_stages = OversamplingFactor::Times8.num_stages();
+        let mut oversampler = Self {
+            factor,
+            phase,
+            minimum_stages: (0..max_stages)
+                .map(|stage| Stage::new(OversamplingPhase::Minimum, stage))
+                .collect(),
+            linear_stages: (0..max_stages)
+                .map(|stage| Stage::new(OversamplingPhase::Linear, stage))
+                .collect(),
+            padding: [0.0; MAX_FACTOR],
+            padding_len: 0,
+            padding_pos: 0,
+            latency: 0,
+        };
+        oversampler.update_latency();
+        oversampler
+    }
+
+    /// Switches to another factor or phase. The filters start from silence again.
+    pub fn set_mode(&mut self, factor: OversamplingFactor, phase: OversamplingPhase) {
+        if self.factor != factor || self.phase != phase {
+            self.factor = factor;
+            self.phase = phase;
+            self.update_latency();
+            self.reset();
+        }
+    }
+
+    fn active_stages(&mut self) -> &mut [Stage] {
+        let num_stages = self.factor.num_stages();
+        match self.phase {
+            OversamplingPhase::Minimum => &mut self.minimum_stages[..num_stages],
+            OversamplingPhase::Linear => &mut self.linear_stages[..num_stages],
+        }
+    }
+
+    fn update_latency(&mut self) {
+        let num_stages = self.factor.num_stages();
+        let factor_len = self.factor.factor();
+        let phase = self.phase;
+        let stages = self.active_stages();
+
+        // Stage `i` runs at `2^(i + 1)` times the host rate, and both its filters add delay
+        let oversampled_latency: f32 = stages
+            .iter()
+            .enumerate()
+            .map(|(i, stage)| {
+                (stage.up.latency() + stage.down.latency()) * (1 << (num_stages - i - 1)) as f32
+            })
+            .sum();
+
+        let (padding_len, latency) = match phase {
+            OversamplingPhase::Linear => {
+                let total = oversampled_latency.round() as usize;
+                let padding_len = (factor_len - total % factor_len) % factor_len;
+                (padding_len, ((total + padding_len) / factor_len) as u32)
+            }
+            OversamplingPhase::Minimum => {
+                (0, (oversampled_latency / factor_len as f32).round() as u32)
+            }
+        };
+        self.padding_len = padding_len;
+        self.latency = latency;
+    }
+
+    /// The largest latency any factor and phase can have, for sizing delay lines up front.
+    pub fn max_latency_samples() -> u32 {
+        [OversamplingPhase::Minimum, OversamplingPhase::Linear]
+            .into_iter()
+            .map(|phase| Oversampler::new(OversamplingFactor::Times8, phase).latency_samples())
+            .max()
+            .unwrap_or(0)
+    }
+
+    pub fn factor(&self) -> OversamplingFactor {
+        self.factor
+    }
+
+    pub fn phase(&self) -> OversamplingPhase {
+        self.phase
+    }
+
+    /// The delay introduced by the filters, in samples at the host rate.
+    pub fn latency_samples(&self) -> u32 {
+        self.latency
+    }
+
+    /// Upsamples `input`, runs `f` on every oversampled sample and downsamples the result.
+    pub fn process(&mut self, input: f32, mut f: impl FnMut(f32) -> f32) -> f32 {
+        if self.factor == OversamplingFactor::Off {
+            return f(input);
+        }
+
+        let mut buffer = [0.0; MAX_FACTOR];
+        let mut upsampled = [0.0; MAX_FACTOR];
+        buffer[0] = input;
+
+        // Each stage doubles the number of samples
+        let mut len = 1;
+        for stage in self.active_stages().iter_mut() {
+            for i in 0..len {
+                let [even, odd] = stage.up.upsample(buffer[i]);
+                upsampled[2 * i] = even;
+                upsampled[2 * i + 1] = odd;
+            }
+            len *= 2;
+            buffer[..len].copy_from_slice(&upsampled[..len]);
+        }
+
+        for sample in buffer[..len].iter_mut() {
+            *sample = f(*sample);
+
+            if self.padding_len > 0 {
+                let delayed = self.padding[self.padding_pos];
+                self.padding[self.padding_pos] = *sample;
+                self.padding_pos = (self.padding_pos + 1) % self.padding_len;
+                *sample = delayed;
+            }
+        }
+
+        for stage in self.active_stages().iter_mut().rev() {
+            len /= 2;
+            for i in 0..len {
+                buffer[i] = stage.down.downsample([buffer[2 * i], buffer[2 * i + 1]]);
+            }
+        }
+
+        buffer[0]
+    }
+
+    pub fn reset(&mut self) {
+        self.minimum_stages.iter_mut().for_each(Stage::reset);
+        self.linear_stages.iter_mut().for_each(Stage::reset);
+        self.padding = [0.0; MAX_FACTOR];
+        self.padding_pos = 0;
+    }
+}
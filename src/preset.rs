@@ -0,0 +1,259 @@
+use nih_plug::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::device::KVPChannelPluginParams;
+
+/// Bumped whenever the meaning of a stored value changes. Files from newer versions are
+/// rejected rather than loaded wrong.
+pub const PRESET_VERSION: u32 = 1;
+const PRESET_EXTENSION: &str = "json";
+pub const USER_CATEGORY: &str = "User";
+// Parameter groups that belong to the session rather than the sound: the console seat and
+// the analyzer display. Presets neither store nor change them.
+const SESSION_PARAM_PREFIXES: [&str; 2] = ["console_", "analyzer_"];
+
+const FACTORY_PRESETS: [&str; 8] = [
+    include_str!("../presets/factory/lead_vocal_polished.json"),
+    include_str!("../presets/factory/lead_vocal_airy.json"),
+    include_str!("../presets/factory/rap_vocal_upfront.json"),
+    include_str!("../presets/factory/rap_vocal_dark_grit.json"),
+    include_str!("../presets/factory/backing_vocals_tucked.json"),
+    include_str!("../presets/factory/backing_vocals_soft_stack.json"),
+    include_str!("../presets/factory/podcast_broadcast.json"),
+    include_str!("../presets/factory/podcast_phone_guest.json"),
+];
+
+/// A stored parameter value. Numbers are plain values, enums are stored by variant name so
+/// the files stay readable and survive variants being reordered.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum PresetValue {
+    Plain(f32),
+    Name(String),
+}
+
+/// A snapshot of every parameter that shapes the sound, keyed by parameter id. Parameters a
+/// preset doesn't mention are reset to their defaults when it's loaded.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Preset {
+    pub version: u32,
+    pub name: String,
+    pub category: String,
+    pub params: BTreeMap<String, PresetValue>,
+}
+
+#[derive(Debug)]
+pub enum PresetError {
+    Io(io::Error),
+    Json(serde_json::Error),
+    UnsupportedVersion(u32),
+    NoPresetDirectory,
+}
+
+impl fmt::Display for PresetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PresetError::Io(err) => write!(f, "{err}"),
+            PresetError::Json(err) => write!(f, "invalid preset: {err}"),
+            PresetError::UnsupportedVersion(version) => {
+                write!(f, "preset version {version} is newer than this plugin")
+            }
+            PresetError::NoPresetDirectory => write!(f, "no config directory for user presets"),
+        }
+    }
+}
+
+impl From<io::Error> for PresetError {
+    fn from(err: io::Error) -> Self {
+        PresetError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for PresetError {
+    fn from(err: serde_json::Error) -> Self {
+        PresetError::Json(err)
+    }
+}
+
+impl Preset {
+    /// Captures the current value of every parameter that shapes the sound.
+    pub fn capture(name: &str, category: &str, params: &KVPChannelPluginParams) -> Self {
+        let params = preset_params(params)
+            .map(|(id, param_ptr)| {
+                // SAFETY: The pointers come from `params`, which outlives this function
+                let value = unsafe {
+                    match param_ptr {
+                        ParamPtr::EnumParam(_) => PresetValue::Name(param_ptr.normalized_value_to_string(
+                            param_ptr.unmodulated_normalized_value(),
+                            false,
+                        )),
+                        _ => PresetValue::Plain(param_ptr.unmodulated_plain_value()),
+                    }
+                };
+                (id, value)
+            })
+            .collect();
+
+        Self {
+            version: PRESET_VERSION,
+            name: name.to_string(),
+            category: category.to_string(),
+            params,
+        }
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, PresetError> {
+        let preset: Preset = serde_json::from_str(json)?;
+        if preset.version > PRESET_VERSION {
+            return Err(PresetError::UnsupportedVersion(preset.version));
+        }
+        Ok(preset)
+    }
+
+    pub fn to_json(&self) -> Result<String, PresetError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// The normalized value this preset sets every parameter in `params` that shapes the sound
+    /// to. Unknown ids are ignored, unparsable values fall back to the parameter's default.
+    pub fn normalized_values(&self, params: &KVPChannelPluginParams) -> Vec<(ParamPtr, f32)> {
+        preset_params(params)
+            .map(|(id, param_ptr)| {
+                // SAFETY: The pointers come from `params`, which outlives this function
+                let normalized = unsafe {
+                    match self.params.get(&id) {
+                        Some(PresetValue::Plain(plain)) => Some(param_ptr.preview_normalized(*plain)),
+                        Some(PresetValue::Name(name)) => param_ptr.string_to_normalized_value(name),
+                        None => None,
+                    }
+                    .unwrap_or_else(|| param_ptr.default_normalized_value())
+                };
+                (param_ptr, normalized)
+            })
+            .collect()
+    }
+
+    /// Whether the name or category contains `query`, ignoring case.
+    pub fn matches(&self, query: &str) -> bool {
+        let query = query.to_lowercase();
+        self.name.to_lowercase().contains(&query) || self.category.to_lowercase().contains(&query)
+    }
+}
+
+// Every parameter presets store, leaving out the session's own groups
+fn preset_params(params: &KVPChannelPluginParams) -> impl Iterator<Item = (String, ParamPtr)> {
+    params
+        .param_map()
+        .into_iter()
+        .filter(|(id, _, _)| !SESSION_PARAM_PREFIXES.iter().any(|prefix| id.starts_with(prefix)))
+        .map(|(id, param_ptr, _)| (id, param_ptr))
+}
+
+/// The presets that ship with the plugin, in browsing order.
+pub fn factory_presets() -> Vec<Preset> {
+    FACTORY_PRESETS
+        .iter()
+        .map(|json| Preset::from_json(json).expect("Invalid factory preset"))
+        .collect()
+}
+
+/// Where user presets are saved, inside the platform's config directory.
+pub fn user_preset_dir() -> Option<PathBuf> {
+    let config_dir = if cfg!(target_os = "windows") {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        std::env::var_os("HOME")
+            .map(|home| PathBuf::from(home).join("Library").join("Application Support"))
+    } else {
+        std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+    }?;
+
+    Some(config_dir.join("KVP Studios").join("KVP Channel").join("Presets"))
+}
+
+/// All user presets sorted by name. Files that can't be read are skipped.
+pub fn load_user_presets() -> Vec<Preset> {
+    let Some(entries) = user_preset_dir().and_then(|dir| fs::read_dir(dir).ok()) else {
+        return Vec::new();
+    };
+
+    let mut presets: Vec<Preset> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == PRESET_EXTENSION))
+        .filter_map(|path| load_preset(&path).ok())
+        .collect();
+    presets.sort_by_key(|preset| preset.name.to_lowercase());
+    presets
+}
+
+pub fn load_preset(path: &Path) -> Result<Preset, PresetError> {
+    Preset::from_json(&fs::read_to_string(path)?)
+}
+
+/// Writes `preset` to the user preset directory, replacing any preset with the same name.
+pub fn save_user_preset(preset: &Preset) -> Result<PathBuf, PresetError> {
+    let dir = user_preset_dir().ok_or(PresetError::NoPresetDirectory)?;
+    fs::create_dir_all(&dir)?;
+
+    // Keep the file name portable, the real name is stored inside the file
+    let file_name: String = preset
+        .name
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == ' ' || c == '-' { c } else { '_' })
+        .collect();
+    let path = dir.join(file_name.trim()).with_extension(PRESET_EXTENSION);
+    fs::write(&path, preset.to_json()?)?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn factory_presets_only_set_existing_params() {
+        let params = KVPChannelPluginParams::default();
+        let param_map = params.param_map();
+
+        for preset in factory_presets() {
+            for (id, value) in &preset.params {
+                let param_ptr = param_map
+                    .iter()
+                    .find(|(param_id, _, _)| param_id == id)
+                    .map(|(_, param_ptr, _)| *param_ptr);
+                let Some(param_ptr) = param_ptr else {
+                    panic!("{} sets the unknown parameter {id}", preset.name);
+                };
+
+                if let PresetValue::Name(name) = value {
+                    // SAFETY: The pointer comes from `params`, which outlives this loop
+                    let normalized = unsafe { param_ptr.string_to_normalized_value(name) };
+                    assert!(
+                        normalized.is_some(),
+                        "{} sets {id} to the unknown value {name}",
+                        preset.name
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn presets_leave_the_session_params_alone() {
+        let params = KVPChannelPluginParams::default();
+        let preset = Preset::capture("Test", USER_CATEGORY, &params);
+
+        let is_session_param =
+            |id: &String| SESSION_PARAM_PREFIXES.iter().any(|prefix| id.starts_with(prefix));
+        assert!(params.param_map().iter().any(|(id, _, _)| is_session_param(id)));
+        assert!(!preset.params.keys().any(is_session_param));
+        assert_eq!(preset.normalized_values(&params).len(), preset.params.len());
+    }
+}
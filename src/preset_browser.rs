@@ -0,0 +1,218 @@
+use nih_plug_vizia::vizia::prelude::*;
+use nih_plug_vizia::widgets::RawParamEvent;
+use std::sync::Arc;
+
+use crate::device::KVPChannelPluginParams;
+use crate::preset::{self, Preset, USER_CATEGORY};
+
+/// The editor's preset state: the factory presets followed by the user's, the one loaded
+/// last and the browser's search and save fields.
+#[derive(Lens)]
+pub struct PresetBrowser {
+    #[lens(ignore)]
+    params: Arc<KVPChannelPluginParams>,
+    presets: Vec<Preset>,
+    current: Option<usize>,
+    current_name: String,
+    search: String,
+    save_name: String,
+    // Result of the last save
+    status: String,
+    // Bumped whenever the list needs to be rebuilt
+    revision: usize,
+}
+
+pub enum PresetEvent {
+    Previous,
+    Next,
+    Load(usize),
+    Search(String),
+    SetSaveName(String),
+    Save,
+}
+
+impl PresetBrowser {
+    pub fn new(params: Arc<KVPChannelPluginParams>) -> Self {
+        Self {
+            params,
+            presets: Self::all_presets(),
+            current: None,
+            current_name: String::from("No Preset"),
+            search: String::new(),
+            save_name: String::new(),
+            status: String::new(),
+            revision: 0,
+        }
+    }
+
+    fn all_presets() -> Vec<Preset> {
+        let mut presets = preset::factory_presets();
+        presets.extend(preset::load_user_presets());
+        presets
+    }
+
+    /// Sets every parameter to the preset's value as a single gesture per parameter, so the
+    /// host records the change like any other edit.
+    fn load(&mut self, cx: &mut EventContext, idx: usize) {
+        let Some(preset) = self.presets.get(idx) else {
+            return;
+        };
+        for (param_ptr, normalized) in preset.normalized_values(&self.params) {
+            cx.emit(RawParamEvent::BeginSetParameter(param_ptr));
+            cx.emit(RawParamEvent::SetParameterNormalized(param_ptr, normalized));
+            cx.emit(RawParamEvent::EndSetParameter(param_ptr));
+        }
+
+        self.current_name = format!("{} / {}", preset.category, preset.name);
+        self.current = Some(idx);
+        self.revision += 1;
+    }
+
+    fn save(&mut self) {
+        let name = self.save_name.trim();
+        if name.is_empty() {
+            self.status = String::from("Enter a name to save");
+            return;
+        }
+
+        let preset = Preset::capture(name, USER_CATEGORY, &self.params);
+        match preset::save_user_preset(&preset) {
+            Ok(_) => {
+                self.presets = Self::all_presets();
+                self.current = self
+                    .presets
+                    .iter()
+                    .position(|other| other.category == USER_CATEGORY && other.name == preset.name);
+                self.current_name = format!("{} / {}", preset.category, preset.name);
+                self.status = format!("Saved \"{}\"", preset.name);
+            }
+            Err(err) => self.status = format!("Saving failed: {err}"),
+        }
+        self.revision += 1;
+    }
+}
+
+impl Model for PresetBrowser {
+    fn event(&mut self, cx: &mut EventContext, event: &mut Event) {
+        event.map(|preset_event, _| match preset_event {
+            PresetEvent::Previous => {
+                let num_presets = self.presets.len();
+                let idx = match self.current {
+                    Some(idx) => (idx + num_presets - 1) % num_presets,
+                    None => num_presets - 1,
+                };
+                self.load(cx, idx);
+            }
+            PresetEvent::Next => {
+                let idx = match self.current {
+                    Some(idx) => (idx + 1) % self.presets.len(),
+                    None => 0,
+                };
+                self.load(cx, idx);
+            }
+            PresetEvent::Load(idx) => self.load(cx, *idx),
+            PresetEvent::Search(search) => {
+                self.search = search.clone();
+                self.revision += 1;
+            }
+            PresetEvent::SetSaveName(name) => self.save_name = name.clone(),
+            PresetEvent::Save => self.save(),
+        });
+    }
+}
+
+/// The header's preset bar: previous and next buttons around the current preset's name, which
+/// opens a searchable list of all presets with a field for saving the current settings.
+pub fn preset_bar(cx: &mut Context) -> Handle<HStack> {
+    HStack::new(cx, |cx| {
+        Label::new(cx, "<")
+            .class("button")
+            .width(Pixels(22.0))
+            .text_align(TextAlign::Center)
+            .cursor(CursorIcon::Hand)
+            .on_press(|cx| cx.emit(PresetEvent::Previous));
+
+        Dropdown::new(
+            cx,
+            |cx| Label::new(cx, PresetBrowser::current_name).class("preset-name"),
+            |cx| {
+                VStack::new(cx, |cx| {
+                    Textbox::new(cx, PresetBrowser::search)
+                        .on_edit(|cx, text| cx.emit(PresetEvent::Search(text)))
+                        .width(Stretch(1.0))
+                        .height(Pixels(22.0));
+
+                    ScrollView::new(cx, 0.0, 0.0, false, true, |cx| {
+                        Binding::new(cx, PresetBrowser::revision, |cx, _| preset_list(cx));
+                    })
+                    .height(Pixels(220.0));
+
+                    HStack::new(cx, |cx| {
+                        Textbox::new(cx, PresetBrowser::save_name)
+                            .on_edit(|cx, text| cx.emit(PresetEvent::SetSaveName(text)))
+                            .width(Stretch(1.0));
+                        Label::new(cx, "SAVE")
+                            .class("button")
+                            .width(Pixels(48.0))
+                            .text_align(TextAlign::Center)
+                            .cursor(CursorIcon::Hand)
+                            .on_press(|cx| cx.emit(PresetEvent::Save));
+                    })
+                    .height(Pixels(22.0))
+                    .col_between(Pixels(4.0));
+
+                    Label::new(cx, PresetBrowser::status).class("readout");
+                })
+                .width(Pixels(240.0))
+                .height(Auto)
+                .child_space(Pixels(4.0))
+                .row_between(Pixels(4.0));
+            },
+        )
+        .class("preset-menu")
+        .width(Stretch(1.0))
+        .height(Pixels(22.0));
+
+        Label::new(cx, ">")
+            .class("button")
+            .width(Pixels(22.0))
+            .text_align(TextAlign::Center)
+            .cursor(CursorIcon::Hand)
+            .on_press(|cx| cx.emit(PresetEvent::Next));
+    })
+    .height(Auto)
+    .col_between(Pixels(4.0))
+    .child_top(Stretch(1.0))
+    .child_bottom(Stretch(1.0))
+}
+
+// The presets matching the search, under a heading whenever the category changes
+fn preset_list(cx: &mut Context) {
+    let presets = PresetBrowser::presets.get(cx);
+    let search = PresetBrowser::search.get(cx);
+    let current = PresetBrowser::current.get(cx);
+
+    VStack::new(cx, |cx| {
+        let mut category = None;
+        for (idx, preset) in presets.iter().enumerate() {
+            if !preset.matches(&search) {
+                continue;
+            }
+            if category != Some(&preset.category) {
+                category = Some(&preset.category);
+                Label::new(cx, preset.category.as_str()).class("section-label");
+            }
+
+            Label::new(cx, preset.name.as_str())
+                .class("menu-item")
+                .toggle_class("current", current == Some(idx))
+                .width(Stretch(1.0))
+                .cursor(CursorIcon::Hand)
+                .on_press(move |cx| {
+                    cx.emit(PresetEvent::Load(idx));
+                    cx.emit(PopupEvent::Close);
+                });
+        }
+    })
+    .height(Auto);
+}
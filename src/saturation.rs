@@ -0,0 +1,172 @@
+use std::f32::consts::PI;
+
+// Stateful saturation models for the colorizer. Unlike the static curves in `colorizer.rs`
+// these have memory, so they can't use ADAA and rely on oversampling for anti-aliasing.
+
+/// Tape-style magnetic hysteresis. The magnetization follows the input through a smoothed
+/// play operator, so it lags behind on direction changes and traces a loop whose width
+/// grows with the saturation amount.
+pub struct Hysteresis {
+    magnetization: f32,
+    relax_coeff: f32,
+}
+
+// How much of the output comes from the lagging magnetization rather than the input
+const HYSTERESIS_BLEND: f32 = 0.35;
+// The magnetization slowly relaxes towards the input so quiet passages don't leave an offset
+const HYSTERESIS_RELAX_TIME: f32 = 0.05;
+
+impl Hysteresis {
+    pub fn new(sample_rate: f32) -> Self {
+        let mut hysteresis = Self {
+            magnetization: 0.0,
+            relax_coeff: 0.0,
+        };
+        hysteresis.set_sample_rate(sample_rate);
+        hysteresis
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.relax_coeff = 1.0 - (-1.0 / (HYSTERESIS_RELAX_TIME * sample_rate)).exp();
+    }
+
+    pub fn process(&mut self, input: f32, saturation: f32) -> f32 {
+        let width = 0.05 + saturation * 0.5;
+
+        // Inside the loop the magnetization sticks, outside of it it trails the input by
+        // roughly `width`
+        let diff = input - self.magnetization;
+        self.magnetization = input - width * (diff / width).tanh();
+        self.magnetization += self.relax_coeff * (input - self.magnetization);
+
+        input.tanh() * (1.0 - HYSTERESIS_BLEND) + self.magnetization.tanh() * HYSTERESIS_BLEND
+    }
+
+    pub fn reset(&mut self) {
+        self.magnetization = 0.0;
+    }
+}
+
+/// An output transformer. The core saturates on flux, which is the integral of the voltage,
+/// so low frequencies drive it into saturation much sooner than the rest of the spectrum.
+pub struct Transformer {
+    lowpass_coeff: f32,
+    low: f32,
+}
+
+// Below this frequency the signal counts towards the core flux
+const TRANSFORMER_CROSSOVER_HZ: f32 = 150.0;
+
+impl Transformer {
+    pub fn new(sample_rate: f32) -> Self {
+        let mut transformer = Self {
+            lowpass_coeff: 0.0,
+            low: 0.0,
+        };
+        transformer.set_sample_rate(sample_rate);
+        transformer
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.lowpass_coeff = 1.0 - (-2.0 * PI * TRANSFORMER_CROSSOVER_HZ / sample_rate).exp();
+    }
+
+    pub fn process(&mut self, input: f32, saturation: f32) -> f32 {
+        self.low += self.lowpass_coeff * (input - self.low);
+        let high = input - self.low;
+
+        // Unity gain for small signals, increasingly compressed lows as the level rises
+        let drive = 1.0 + saturation * 6.0;
+        let low = (self.low * drive).tanh() / drive;
+
+        // The rest of the spectrum only sees a very gentle curve
+        let output = high + low;
+        output / (1.0 + saturation * 0.1 * output.abs())
+    }
+
+    pub fn reset(&mut self) {
+        self.low = 0.0;
+    }
+}
+
+/// A passive RC diode clipper with a pair of antiparallel silicon diodes. The implicit
+/// Euler discretization of the circuit has no closed form solution, so every sample is
+/// solved with a few bracketed Newton iterations starting from the previous capacitor
+/// voltage.
+pub struct DiodeClipper {
+    sample_period: f64,
+    voltage: f64,
+}
+
+const DIODE_RESISTANCE: f64 = 1.0e3;
+const DIODE_CAPACITANCE: f64 = 1.0e-8;
+const DIODE_SATURATION_CURRENT: f64 = 2.52e-9;
+const DIODE_THERMAL_VOLTAGE: f64 = 25.85e-3;
+const DIODE_MAX_ITERATIONS: usize = 16;
+const DIODE_TOLERANCE: f64 = 1.0e-7;
+const DIODE_MAX_RATIO: f64 = 80.0;
+
+impl DiodeClipper {
+    pub fn new(sample_rate: f32) -> Self {
+        Self {
+            sample_period: 1.0 / sample_rate as f64,
+            voltage: 0.0,
+        }
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_period = 1.0 / sample_rate as f64;
+    }
+
+    pub fn process(&mut self, input: f32, saturation: f32) -> f32 {
+        // Map the signal to volts, the diodes start conducting at around 0.3-0.6 V
+        let scale = 0.25 + saturation as f64 * 2.0;
+        let x = input as f64 * scale;
+
+        let rc = self.sample_period / (DIODE_RESISTANCE * DIODE_CAPACITANCE);
+        let is = 2.0 * self.sample_period * DIODE_SATURATION_CURRENT / DIODE_CAPACITANCE;
+
+        // With the diodes ignored the circuit is linear, and the actual solution always lies
+        // between zero and that linear solution. The bracket keeps Newton from overshooting
+        // into the exponential region on large jumps.
+        let prev = self.voltage;
+        let linear = (prev + rc * x) / (1.0 + rc);
+        let (mut low, mut high) = if linear < 0.0 { (linear, 0.0) } else { (0.0, linear) };
+
+        let mut v = prev.clamp(low, high);
+        for _ in 0..DIODE_MAX_ITERATIONS {
+            // Far outside of the conducting region Newton only crawls along the exponential,
+            // so bisect instead. This also keeps the hyperbolic functions from overflowing.
+            let ratio = v / DIODE_THERMAL_VOLTAGE;
+            let out_of_range = ratio.abs() > DIODE_MAX_RATIO;
+            let ratio = ratio.clamp(-DIODE_MAX_RATIO, DIODE_MAX_RATIO);
+            let g = v - prev - rc * (x - v) + is * ratio.sinh();
+            let dg = 1.0 + rc + is * ratio.cosh() / DIODE_THERMAL_VOLTAGE;
+
+            // g is monotonically increasing, so its sign tells which side of the root we're on
+            if g > 0.0 {
+                high = v;
+            } else {
+                low = v;
+            }
+
+            let mut next = v - g / dg;
+            if out_of_range || next <= low || next >= high {
+                next = 0.5 * (low + high);
+            }
+
+            let step = next - v;
+            v = next;
+            if step.abs() < DIODE_TOLERANCE {
+                break;
+            }
+        }
+        self.voltage = v;
+
+        (v / scale) as f32
+    }
+
+    pub fn reset(&mut self) {
+        self.voltage = 0.0;
+    }
+}
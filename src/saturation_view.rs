@@ -0,0 +1,227 @@
+use nih_plug::prelude::*;
+use nih_plug_vizia::vizia::prelude::*;
+use nih_plug_vizia::vizia::vg;
+use std::cell::RefCell;
+use std::f32::consts::PI;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::colorizer::Colorizer;
+use crate::colorizor_device::{apply_params, ColorizerDeviceParams};
+use crate::meters::Meters;
+use crate::oversampler::Oversampler;
+use crate::tape::WOW_FLUTTER_DELAY;
+use crate::theme::{Palette, ThemeState};
+use crate::ui::{draw_background, start_refresh_timer, vg_color};
+
+// Samples per cycle of the slow sine that traces the curve, slow enough that the models
+// with memory draw a curve rather than a loop
+const CURVE_PERIOD: usize = 8192;
+// Output range of the curve plot
+const CURVE_RANGE: f32 = 1.25;
+// The test tone sits at a whole number of samples per cycle so every harmonic lands exactly
+// on a DFT bin. It runs through the same oversampling as the audio, otherwise the harmonics
+// past Nyquist would fold straight back onto the bins being measured.
+const TONE_PERIOD: usize = 64;
+const TONE_AMPLITUDE: f32 = 0.5;
+const TONE_SETTLE_CYCLES: usize = 64;
+const TONE_MEASURE_CYCLES: usize = 48;
+const NUM_HARMONICS: usize = 9;
+// Range of the harmonic bars relative to the fundamental
+const HARMONICS_RANGE_DB: f32 = 100.0;
+const SATURATION_REFRESH: Duration = Duration::from_millis(100);
+
+/// The colorizer's transfer curve for the current type and drive next to the levels of the
+/// 2nd to 9th harmonics it adds to a sine. Both are measured on the editor's own colorizer
+/// and oversampler with the same parameters as the audio thread.
+pub struct SaturationView {
+    params: Arc<ColorizerDeviceParams>,
+    meters: Arc<Meters>,
+    theme: Arc<ThemeState>,
+    colorizer: RefCell<Colorizer>,
+    oversampler: RefCell<Oversampler>,
+}
+
+impl SaturationView {
+    pub fn new(
+        cx: &mut Context,
+        params: Arc<ColorizerDeviceParams>,
+        meters: Arc<Meters>,
+        theme: Arc<ThemeState>,
+    ) -> Handle<Self> {
+        let colorizer = RefCell::new(Colorizer::new(meters.sample_rate()));
+        let oversampler = RefCell::new(Oversampler::new(
+            params.oversampling.value(),
+            params.oversampling_phase.value(),
+        ));
+        Self {
+            params,
+            meters,
+            theme,
+            colorizer,
+            oversampler,
+        }
+        .build(cx, |cx| start_refresh_timer(cx, SATURATION_REFRESH))
+    }
+
+    // Brings the colorizer and the oversampler in line with the parameters and clears their
+    // state, with the colorizer running at the oversampled rate like on the audio thread
+    fn prepare(&self, colorizer: &mut Colorizer, oversampler: &mut Oversampler) {
+        let factor = self.params.oversampling.value();
+        oversampler.set_mode(factor, self.params.oversampling_phase.value());
+
+        let sample_rate = self.meters.sample_rate();
+        colorizer.set_sample_rate(sample_rate * factor.factor() as f32);
+        colorizer
+            .tape
+            .set_delay((WOW_FLUTTER_DELAY * sample_rate).round() * factor.factor() as f32);
+        apply_params(colorizer, &self.params, 0.0);
+        colorizer.reset();
+        oversampler.reset();
+    }
+
+    /// Traces one cycle of a full scale sine through the curve, after one cycle to settle.
+    fn trace_curve(
+        &self,
+        colorizer: &mut Colorizer,
+        oversampler: &mut Oversampler,
+    ) -> Vec<(f32, f32)> {
+        self.prepare(colorizer, oversampler);
+        let sine = |idx: usize| (2.0 * PI * idx as f32 / CURVE_PERIOD as f32).sin();
+        for idx in 0..CURVE_PERIOD {
+            colorizer.transfer(sine(idx));
+        }
+        (0..CURVE_PERIOD)
+            .map(|idx| (sine(idx), colorizer.transfer(sine(idx))))
+            .collect()
+    }
+
+    /// Levels of the 2nd to 9th harmonics of a test sine relative to the fundamental in dB.
+    fn measure_harmonics(
+        &self,
+        colorizer: &mut Colorizer,
+        oversampler: &mut Oversampler,
+    ) -> [f32; NUM_HARMONICS - 1] {
+        self.prepare(colorizer, oversampler);
+        let sine = |idx: usize| {
+            TONE_AMPLITUDE * (2.0 * PI * idx as f32 / TONE_PERIOD as f32).sin()
+        };
+        for idx in 0..TONE_SETTLE_CYCLES * TONE_PERIOD {
+            oversampler.process(sine(idx), |x| colorizer.process(x));
+        }
+
+        let mut bins = [(0.0f32, 0.0f32); NUM_HARMONICS];
+        for idx in 0..TONE_MEASURE_CYCLES * TONE_PERIOD {
+            let output = oversampler.process(sine(idx), |x| colorizer.process(x));
+            for (harmonic, (re, im)) in bins.iter_mut().enumerate() {
+                let phase = 2.0 * PI * ((harmonic + 1) * idx % TONE_PERIOD) as f32
+                    / TONE_PERIOD as f32;
+                *re += output * phase.cos();
+                *im -= output * phase.sin();
+            }
+        }
+
+        let level = |(re, im): (f32, f32)| re.hypot(im).max(util::MINUS_INFINITY_GAIN);
+        let fundamental = level(bins[0]);
+        std::array::from_fn(|idx| util::gain_to_db(level(bins[idx + 1]) / fundamental))
+    }
+
+    fn draw_curve(
+        canvas: &mut Canvas,
+        bounds: BoundingBox,
+        palette: &Palette,
+        points: &[(f32, f32)],
+    ) {
+        let mut axes = vg::Path::new();
+        axes.move_to(bounds.x, bounds.y + bounds.h / 2.0);
+        axes.line_to(bounds.x + bounds.w, bounds.y + bounds.h / 2.0);
+        axes.move_to(bounds.x + bounds.w / 2.0, bounds.y);
+        axes.line_to(bounds.x + bounds.w / 2.0, bounds.y + bounds.h);
+        canvas.stroke_path(&axes, &vg::Paint::color(vg_color(palette.border)));
+
+        let to_point = |(input, output): (f32, f32)| {
+            let output = (output / CURVE_RANGE).clamp(-1.0, 1.0);
+            (
+                bounds.x + bounds.w * (input + 1.0) / 2.0,
+                bounds.y + bounds.h * (1.0 - output) / 2.0,
+            )
+        };
+        let mut curve = vg::Path::new();
+        for (idx, &point) in points.iter().enumerate() {
+            let (x, y) = to_point(point);
+            if idx == 0 {
+                curve.move_to(x, y);
+            } else {
+                curve.line_to(x, y);
+            }
+        }
+        let mut paint = vg::Paint::color(vg_color(palette.highlight));
+        paint.set_line_width(2.0);
+        canvas.stroke_path(&curve, &paint);
+    }
+
+    fn draw_harmonics(
+        canvas: &mut Canvas,
+        bounds: BoundingBox,
+        palette: &Palette,
+        harmonics: &[f32],
+    ) {
+        let bar_width = bounds.w / harmonics.len() as f32;
+        for (idx, db) in harmonics.iter().enumerate() {
+            let height = bounds.h * ((db + HARMONICS_RANGE_DB) / HARMONICS_RANGE_DB).clamp(0.0, 1.0);
+            let color = if idx % 2 == 0 {
+                palette.highlight
+            } else {
+                palette.accent
+            };
+            let mut bar = vg::Path::new();
+            bar.rect(
+                bounds.x + idx as f32 * bar_width + 1.0,
+                bounds.y + bounds.h - height,
+                (bar_width - 2.0).max(1.0),
+                height,
+            );
+            canvas.fill_path(&bar, &vg::Paint::color(vg_color(color)));
+        }
+    }
+}
+
+impl View for SaturationView {
+    fn element(&self) -> Option<&'static str> {
+        Some("saturation-display")
+    }
+
+    fn draw(&self, cx: &mut DrawContext, canvas: &mut Canvas) {
+        let bounds = cx.bounds();
+        if bounds.w == 0.0 || bounds.h == 0.0 {
+            return;
+        }
+        let palette = self.theme.palette();
+
+        draw_background(canvas, bounds, palette);
+
+        let mut colorizer = self.colorizer.borrow_mut();
+        let mut oversampler = self.oversampler.borrow_mut();
+        let points = self.trace_curve(&mut colorizer, &mut oversampler);
+        let harmonics = self.measure_harmonics(&mut colorizer, &mut oversampler);
+
+        // The square curve on the left and the harmonics (even ones lighter) on the right
+        let curve_size = bounds.h.min(bounds.w / 2.0) - 8.0;
+        let curve_bounds = BoundingBox {
+            x: bounds.x + 4.0,
+            y: bounds.y + 4.0,
+            w: curve_size,
+            h: curve_size,
+        };
+        let harmonics_x = curve_bounds.x + curve_size + 8.0;
+        let harmonics_bounds = BoundingBox {
+            x: harmonics_x,
+            y: bounds.y + 4.0,
+            w: bounds.x + bounds.w - 4.0 - harmonics_x,
+            h: bounds.h - 8.0,
+        };
+
+        Self::draw_curve(canvas, curve_bounds, palette, &points);
+        Self::draw_harmonics(canvas, harmonics_bounds, palette, &harmonics);
+    }
+}
@@ -0,0 +1,75 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+// Number of instances whose variance seeds are tracked, later ones just keep their seed
+const SEED_REGISTRY_SIZE: usize = 256;
+// Marks an unused slot, seeds are never claimed with this value
+const NO_SEED: u64 = 0;
+
+#[allow(clippy::declare_interior_mutable_const)]
+const EMPTY_SLOT: AtomicU64 = AtomicU64::new(NO_SEED);
+
+// The analog variance seeds claimed by the instances loaded in this process
+static REGISTRY: [AtomicU64; SEED_REGISTRY_SIZE] = [EMPTY_SLOT; SEED_REGISTRY_SIZE];
+
+/// An instance's claim on its analog variance seed. Duplicating a track copies the stored
+/// seed, so the copy finds the seed already claimed and rolls a new one instead of ending up
+/// with the same tolerances. The claim is released again when this is dropped.
+pub struct SeedClaim {
+    index: Option<usize>,
+    seed: u64,
+}
+
+impl SeedClaim {
+    /// Claims `seed`, or a new seed derived from it if another instance already has it.
+    pub fn claim(seed: u64) -> Self {
+        let mut seed = seed;
+        // Every attempt gets a different seed, so this ends once the registry runs out
+        for _ in 0..SEED_REGISTRY_SIZE {
+            if seed != NO_SEED {
+                let Some(index) = REGISTRY.iter().position(|slot| {
+                    slot.compare_exchange(NO_SEED, seed, Ordering::AcqRel, Ordering::Relaxed)
+                        .is_ok()
+                }) else {
+                    // Too many instances to keep track of
+                    return Self { index: None, seed };
+                };
+
+                // Instances that were already running keep their seed, the newcomer moves
+                let taken = REGISTRY
+                    .iter()
+                    .enumerate()
+                    .any(|(other, slot)| other != index && slot.load(Ordering::Acquire) == seed);
+                if !taken {
+                    return Self {
+                        index: Some(index),
+                        seed,
+                    };
+                }
+                REGISTRY[index].store(NO_SEED, Ordering::Release);
+            }
+            seed = next_seed(seed);
+        }
+
+        Self { index: None, seed }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+}
+
+impl Drop for SeedClaim {
+    fn drop(&mut self) {
+        if let Some(index) = self.index {
+            REGISTRY[index].store(NO_SEED, Ordering::Release);
+        }
+    }
+}
+
+// SplitMix64, spreads neighbouring seeds far apart
+fn next_seed(seed: u64) -> u64 {
+    let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
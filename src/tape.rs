@@ -0,0 +1,277 @@
+use biquad::{self, Biquad, ToHertz};
+use nih_plug::prelude::Enum;
+use std::f32::consts::PI;
+
+use crate::device::MAX_SAMPLE_RATE;
+use crate::oversampler::OversamplingFactor;
+use crate::saturation::Hysteresis;
+
+/// The center delay around which wow and flutter modulate, in seconds. It has to cover the
+/// largest combined modulation depth and is reported to the host as latency.
+pub const WOW_FLUTTER_DELAY: f32 = 0.002;
+
+const WOW_MAX_DEPTH: f32 = 0.001; // 1ms at full wow
+const FLUTTER_MAX_DEPTH: f32 = 0.000_15; // 0.15ms at full flutter
+const WOW_RATE: f32 = 0.6; // Hz, capstan eccentricity
+const FLUTTER_RATE: f32 = 7.0; // Hz, scrape and roller flutter
+const FLUTTER_RATE_SECONDARY: f32 = 11.3;
+
+#[derive(Copy, Clone, Enum, PartialEq)]
+pub enum TapeSpeed {
+    #[name = "7.5 ips"]
+    Slow,
+    #[name = "15 ips"]
+    Medium,
+    #[name = "30 ips"]
+    Fast,
+}
+
+impl TapeSpeed {
+    // Head bump center frequency, it scales with the tape speed
+    fn head_bump_freq(&self) -> f32 {
+        match self {
+            TapeSpeed::Slow => 40.0,
+            TapeSpeed::Medium => 60.0,
+            TapeSpeed::Fast => 100.0,
+        }
+    }
+
+    fn head_bump_gain(&self) -> f32 {
+        match self {
+            TapeSpeed::Slow => 3.5,
+            TapeSpeed::Medium => 3.0,
+            TapeSpeed::Fast => 2.0,
+        }
+    }
+
+    // Self-erasure and gap losses take away more top end at lower speeds
+    fn hf_cutoff(&self) -> f32 {
+        match self {
+            TapeSpeed::Slow => 10000.0,
+            TapeSpeed::Medium => 16000.0,
+            TapeSpeed::Fast => 22000.0,
+        }
+    }
+
+    // Slower transports are less stable
+    fn modulation_scale(&self) -> f32 {
+        match self {
+            TapeSpeed::Slow => 1.5,
+            TapeSpeed::Medium => 1.0,
+            TapeSpeed::Fast => 0.7,
+        }
+    }
+
+    // Hiss drops as more tape passes the head per second
+    fn hiss_offset_db(&self) -> f32 {
+        match self {
+            TapeSpeed::Slow => 3.0,
+            TapeSpeed::Medium => 0.0,
+            TapeSpeed::Fast => -3.0,
+        }
+    }
+}
+
+/// A tape machine: hysteresis saturation on the record side, followed by the playback
+/// head's low end bump and top end loss, transport wow and flutter and an optional hiss
+/// floor.
+pub struct TapeMachine {
+    sample_rate: f32,
+    speed: TapeSpeed,
+    hysteresis: Hysteresis,
+    head_bump: biquad::DirectForm1<f32>,
+    hf_loss: biquad::DirectForm1<f32>,
+    // Sized for the longest delay at the highest rate and oversampling factor
+    delay_buffer: Vec<f32>,
+    delay_pos: usize,
+    base_delay: f32,
+    wow: f32,
+    flutter: f32,
+    wow_phase: f32,
+    flutter_phase: f32,
+    flutter_phase_secondary: f32,
+    hiss_enabled: bool,
+    hiss_gain: f32,
+    hiss_lowpass: f32,
+    noise_state: u32,
+}
+
+impl TapeMachine {
+    pub fn new(sample_rate: f32) -> Self {
+        let speed = TapeSpeed::Medium;
+        let mut tape = Self {
+            sample_rate,
+            speed,
+            hysteresis: Hysteresis::new(sample_rate),
+            head_bump: biquad::DirectForm1::new(Self::head_bump_coeffs(speed, sample_rate)),
+            hf_loss: biquad::DirectForm1::new(Self::hf_loss_coeffs(speed, sample_rate)),
+            delay_buffer: vec![0.0; Self::delay_buffer_len(Self::max_delay())],
+            delay_pos: 0,
+            base_delay: 0.0,
+            wow: 0.0,
+            flutter: 0.0,
+            wow_phase: 0.0,
+            flutter_phase: 0.0,
+            flutter_phase_secondary: 0.0,
+            hiss_enabled: false,
+            hiss_gain: 0.0,
+            hiss_lowpass: 0.0,
+            noise_state: 0x2545_f491,
+        };
+        tape.set_delay((WOW_FLUTTER_DELAY * sample_rate).round());
+        tape
+    }
+
+    // The center delay at the highest supported rate, oversampled as far as it goes
+    fn max_delay() -> f32 {
+        (WOW_FLUTTER_DELAY * MAX_SAMPLE_RATE).round() * OversamplingFactor::Times8.factor() as f32
+    }
+
+    // Room for the deepest modulation plus the interpolation taps
+    fn delay_buffer_len(delay_samples: f32) -> usize {
+        (delay_samples * 2.0).ceil() as usize + 4
+    }
+
+    fn head_bump_coeffs(speed: TapeSpeed, sample_rate: f32) -> biquad::Coefficients<f32> {
+        biquad::Coefficients::<f32>::from_params(
+            biquad::Type::PeakingEQ(speed.head_bump_gain()),
+            sample_rate.hz(),
+            speed.head_bump_freq().hz(),
+            1.2,
+        )
+        .unwrap()
+    }
+
+    fn hf_loss_coeffs(speed: TapeSpeed, sample_rate: f32) -> biquad::Coefficients<f32> {
+        // Keep the cutoff below Nyquist when running at the host rate
+        let cutoff = speed.hf_cutoff().min(sample_rate * 0.45);
+        biquad::Coefficients::<f32>::from_params(
+            biquad::Type::LowPass,
+            sample_rate.hz(),
+            cutoff.hz(),
+            biquad::Q_BUTTERWORTH_F32,
+        )
+        .unwrap()
+    }
+
+    fn update_filters(&mut self) {
+        self.head_bump
+            .update_coefficients(Self::head_bump_coeffs(self.speed, self.sample_rate));
+        self.hf_loss
+            .update_coefficients(Self::hf_loss_coeffs(self.speed, self.sample_rate));
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        if self.sample_rate != sample_rate {
+            self.sample_rate = sample_rate;
+            self.hysteresis.set_sample_rate(sample_rate);
+            self.update_filters();
+        }
+    }
+
+    pub fn set_speed(&mut self, speed: TapeSpeed) {
+        if self.speed != speed {
+            self.speed = speed;
+            self.update_filters();
+        }
+    }
+
+    /// Sets the center delay for the wow and flutter modulation in samples. This should
+    /// correspond to a whole number of samples at the host rate so it can be reported as
+    /// latency. Only moves the read position, so this doesn't allocate.
+    pub fn set_delay(&mut self, delay_samples: f32) {
+        self.base_delay = delay_samples.min(Self::max_delay());
+    }
+
+    /// Sets the wow and flutter amounts, both from 0.0 to 1.0.
+    pub fn set_wow_flutter(&mut self, wow: f32, flutter: f32) {
+        self.wow = wow.clamp(0.0, 1.0);
+        self.flutter = flutter.clamp(0.0, 1.0);
+    }
+
+    pub fn set_hiss(&mut self, enabled: bool, level_db: f32) {
+        self.hiss_enabled = enabled;
+        self.hiss_gain = 10.0_f32.powf((level_db + self.speed.hiss_offset_db()) / 20.0);
+    }
+
+    // Cheap xorshift noise in the range [-1, 1]
+    fn next_noise(&mut self) -> f32 {
+        self.noise_state ^= self.noise_state << 13;
+        self.noise_state ^= self.noise_state >> 17;
+        self.noise_state ^= self.noise_state << 5;
+        (self.noise_state as f32 / u32::MAX as f32) * 2.0 - 1.0
+    }
+
+    fn advance_phase(phase: &mut f32, rate: f32, sample_rate: f32) {
+        *phase += rate / sample_rate;
+        if *phase >= 1.0 {
+            *phase -= 1.0;
+        }
+    }
+
+    fn wow_flutter(&mut self, input: f32) -> f32 {
+        if self.delay_buffer.is_empty() {
+            return input;
+        }
+
+        let len = self.delay_buffer.len();
+        self.delay_buffer[self.delay_pos] = input;
+
+        Self::advance_phase(&mut self.wow_phase, WOW_RATE, self.sample_rate);
+        Self::advance_phase(&mut self.flutter_phase, FLUTTER_RATE, self.sample_rate);
+        Self::advance_phase(
+            &mut self.flutter_phase_secondary,
+            FLUTTER_RATE_SECONDARY,
+            self.sample_rate,
+        );
+
+        let scale = self.speed.modulation_scale() * self.sample_rate;
+        let wow_depth = self.wow * WOW_MAX_DEPTH * scale;
+        let flutter_depth = self.flutter * FLUTTER_MAX_DEPTH * scale;
+        let modulation = wow_depth * (2.0 * PI * self.wow_phase).sin()
+            + flutter_depth * (2.0 * PI * self.flutter_phase).sin() * 0.7
+            + flutter_depth * (2.0 * PI * self.flutter_phase_secondary).sin() * 0.3;
+
+        let delay = (self.base_delay + modulation).clamp(1.0, (len - 2) as f32);
+        let read_pos = self.delay_pos as f32 + len as f32 - delay;
+        let index = read_pos.floor() as usize;
+        let frac = read_pos - read_pos.floor();
+        let a = self.delay_buffer[index % len];
+        let b = self.delay_buffer[(index + 1) % len];
+
+        self.delay_pos = (self.delay_pos + 1) % len;
+        a + (b - a) * frac
+    }
+
+    pub fn process(&mut self, input: f32, saturation: f32) -> f32 {
+        // Without any saturation the tape is recorded clean
+        let recorded = if saturation > 0.0 {
+            self.hysteresis.process(input, saturation)
+        } else {
+            input
+        };
+        let played = self.hf_loss.run(self.head_bump.run(recorded));
+        let output = self.wow_flutter(played);
+
+        if self.hiss_enabled {
+            // Tilted towards the top end like real tape hiss. The noise spreads out over the
+            // whole band at higher (oversampled) rates, so scale it to keep the audible level
+            // independent of the rate.
+            let noise = self.next_noise();
+            self.hiss_lowpass += 0.5 * (noise - self.hiss_lowpass);
+            let hiss = noise - 0.5 * self.hiss_lowpass;
+            output + hiss * self.hiss_gain * (self.sample_rate / 44100.0).sqrt()
+        } else {
+            output
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.hysteresis.reset();
+        self.head_bump.reset_state();
+        self.hf_loss.reset_state();
+        self.delay_buffer.iter_mut().for_each(|sample| *sample = 0.0);
+        self.delay_pos = 0;
+        self.hiss_lowpass = 0.0;
+    }
+}
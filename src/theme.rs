@@ -0,0 +1,241 @@
+use nih_plug::params::persist::PersistentField;
+use nih_plug::prelude::Enum;
+use nih_plug_vizia::vizia::prelude::*;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+
+/// The editor's skins. Both the stylesheet rules keyed on the theme's class on the root view and
+/// the views that draw on the canvas take their colors from the theme's `Palette`.
+#[derive(Copy, Clone, Enum, PartialEq, Data)]
+pub enum EditorTheme {
+    #[id = "rust"]
+    #[name = "Rust"]
+    Rust,
+    #[id = "high-contrast"]
+    #[name = "High Contrast"]
+    HighContrast,
+    #[id = "light"]
+    #[name = "Light"]
+    Light,
+}
+
+/// The theme's colors by role, for the widgets as well as for drawing on the canvas.
+pub struct Palette {
+    // Behind the panels
+    pub background: Color,
+    pub accent: Color,
+    // Fills and secondary curves that sit behind the main one
+    pub accent_muted: Color,
+    // The main curve or bar of a display
+    pub highlight: Color,
+    pub panel: Color,
+    pub text: Color,
+    pub border: Color,
+    // Text on accent colored buttons
+    pub on_accent: Color,
+    pub spectrum_pre: Color,
+    pub spectrum_post: Color,
+}
+
+const RUST_PALETTE: Palette = Palette {
+    background: Color::rgba(150, 75, 20, 1),
+    accent: Color::rgba(194, 107, 36, 1),
+    accent_muted: Color::rgba(150, 75, 20, 1),
+    highlight: Color::rgba(226, 141, 63, 1),
+    panel: Color::rgba(40, 32, 28, 1),
+    text: Color::rgba(255, 235, 210, 1),
+    border: Color::rgba(90, 55, 30, 1),
+    on_accent: Color::rgba(255, 235, 210, 1),
+    spectrum_pre: Color::rgba(70, 60, 55, 1),
+    spectrum_post: Color::rgba(95, 62, 38, 1),
+};
+
+const HIGH_CONTRAST_PALETTE: Palette = Palette {
+    background: Color::rgba(0, 0, 0, 1),
+    accent: Color::rgba(255, 200, 0, 1),
+    accent_muted: Color::rgba(120, 95, 0, 1),
+    highlight: Color::rgba(255, 235, 60, 1),
+    panel: Color::rgba(0, 0, 0, 1),
+    text: Color::rgba(255, 255, 255, 1),
+    border: Color::rgba(150, 150, 150, 1),
+    on_accent: Color::rgba(0, 0, 0, 1),
+    spectrum_pre: Color::rgba(70, 70, 70, 1),
+    spectrum_post: Color::rgba(0, 90, 140, 1),
+};
+
+const LIGHT_PALETTE: Palette = Palette {
+    background: Color::rgba(232, 224, 214, 1),
+    accent: Color::rgba(194, 107, 36, 1),
+    accent_muted: Color::rgba(235, 195, 160, 1),
+    highlight: Color::rgba(160, 75, 15, 1),
+    panel: Color::rgba(250, 246, 240, 1),
+    text: Color::rgba(45, 32, 24, 1),
+    border: Color::rgba(200, 182, 162, 1),
+    on_accent: Color::rgba(255, 255, 255, 1),
+    spectrum_pre: Color::rgba(222, 214, 206, 1),
+    spectrum_post: Color::rgba(240, 210, 180, 1),
+};
+
+impl EditorTheme {
+    pub fn name(&self) -> &'static str {
+        Self::variants()[self.to_index()]
+    }
+
+    /// The class the stylesheet keys this theme's rules on.
+    pub fn class(&self) -> &'static str {
+        match self {
+            EditorTheme::Rust => "theme-rust",
+            EditorTheme::HighContrast => "theme-high-contrast",
+            EditorTheme::Light => "theme-light",
+        }
+    }
+
+    pub fn palette(&self) -> &'static Palette {
+        match self {
+            EditorTheme::Rust => &RUST_PALETTE,
+            EditorTheme::HighContrast => &HIGH_CONTRAST_PALETTE,
+            EditorTheme::Light => &LIGHT_PALETTE,
+        }
+    }
+
+    // The rules that color the widgets while this theme's class is set
+    fn stylesheet_rules(&self) -> String {
+        let class = self.class();
+        let palette = self.palette();
+        let [background, text, highlight, accent, accent_muted, panel, border, on_accent] = [
+            palette.background,
+            palette.text,
+            palette.highlight,
+            palette.accent,
+            palette.accent_muted,
+            palette.panel,
+            palette.border,
+            palette.on_accent,
+        ]
+        .map(|color| format!("#{:02x}{:02x}{:02x}", color.r(), color.g(), color.b()));
+
+        format!(
+            "
+.{class} {{
+    background-color: {background};
+}}
+
+.{class} label,
+.{class} .panel-title,
+.{class} .readout,
+.{class} .knob-readout {{
+    color: {text};
+}}
+
+.{class} .title,
+.{class} .section-label,
+.{class} .brand {{
+    color: {highlight};
+}}
+
+.{class} .panel {{
+    background-color: {panel};
+    border-color: {border};
+}}
+
+.{class} .button {{
+    color: {on_accent};
+    background-color: {accent};
+    border-color: {border};
+}}
+
+.{class} .button:hover,
+.{class} .button.selected,
+.{class} .scale-preset:hover {{
+    color: {on_accent};
+    background-color: {highlight};
+}}
+
+.{class} .knob-entry,
+.{class} .preset-menu textbox {{
+    color: {text};
+    background-color: {panel};
+    border-color: {accent};
+}}
+
+.{class} param-slider {{
+    color: {text};
+    border-color: {border};
+}}
+
+.{class} param-slider .fill {{
+    background-color: {accent_muted};
+}}
+
+.{class} .theme-menu,
+.{class} .preset-menu,
+.{class} popup {{
+    background-color: {panel};
+    border-color: {border};
+}}
+
+.{class} .menu-item:hover,
+.{class} .menu-item.current {{
+    background-color: {accent_muted};
+}}
+"
+        )
+    }
+}
+
+/// The colors of every theme, generated from the palettes so the widgets and the canvas can't
+/// drift apart. The sizes all themes share live in `theme.css`.
+pub fn stylesheet() -> &'static str {
+    static STYLESHEET: OnceLock<String> = OnceLock::new();
+    STYLESHEET.get_or_init(|| {
+        (0..EditorTheme::variants().len())
+            .map(|index| EditorTheme::from_index(index).stylesheet_rules())
+            .collect()
+    })
+}
+
+/// The chosen theme, shared between the editor and the plugin state so it's restored with the
+/// session. Stored by the theme's id so reordering the themes doesn't change saved sessions.
+pub struct ThemeState {
+    theme: AtomicUsize,
+}
+
+impl ThemeState {
+    pub fn new(theme: EditorTheme) -> Arc<Self> {
+        Arc::new(Self {
+            theme: AtomicUsize::new(theme.to_index()),
+        })
+    }
+
+    pub fn theme(&self) -> EditorTheme {
+        EditorTheme::from_index(self.theme.load(Ordering::Relaxed))
+    }
+
+    pub fn set_theme(&self, theme: EditorTheme) {
+        self.theme.store(theme.to_index(), Ordering::Relaxed);
+    }
+
+    pub fn palette(&self) -> &'static Palette {
+        self.theme().palette()
+    }
+}
+
+impl<'a> PersistentField<'a, String> for Arc<ThemeState> {
+    fn set(&self, new_value: String) {
+        // Unknown ids from newer versions keep the current theme
+        let index = EditorTheme::ids().and_then(|ids| ids.iter().position(|id| *id == new_value));
+        if let Some(index) = index {
+            self.set_theme(EditorTheme::from_index(index));
+        }
+    }
+
+    fn map<F, R>(&self, f: F) -> R
+    where
+        F: Fn(&String) -> R,
+    {
+        let id = EditorTheme::ids()
+            .map(|ids| ids[self.theme().to_index()])
+            .unwrap_or_default();
+        f(&id.to_string())
+    }
+}
@@ -0,0 +1,118 @@
+use nih_plug::prelude::*;
+use nih_plug_vizia::vizia::prelude::*;
+use nih_plug_vizia::vizia::vg;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::compressor::{Compressor, KNEE_WIDTH_DB};
+use crate::compressor_device::CompressorDeviceParams;
+use crate::meters::GainReductionMeter;
+use crate::theme::ThemeState;
+use crate::ui::{draw_background, start_refresh_timer, vg_color};
+
+// Level range of both axes
+const CURVE_MIN_DB: f32 = -48.0;
+const CURVE_MAX_DB: f32 = 6.0;
+// Pixels between the points the curve is evaluated at
+const CURVE_STEP: f32 = 2.0;
+const CURVE_REFRESH: Duration = Duration::from_millis(33);
+const DOT_RADIUS: f32 = 4.0;
+
+/// The compressor's static input/output curve with the threshold and knee marked, and a dot
+/// following the detector level and the gain reduction it currently causes.
+pub struct TransferCurveView {
+    params: Arc<CompressorDeviceParams>,
+    meter: Arc<GainReductionMeter>,
+    theme: Arc<ThemeState>,
+}
+
+impl TransferCurveView {
+    pub fn new(
+        cx: &mut Context,
+        params: Arc<CompressorDeviceParams>,
+        meter: Arc<GainReductionMeter>,
+        theme: Arc<ThemeState>,
+    ) -> Handle<Self> {
+        Self {
+            params,
+            meter,
+            theme,
+        }
+        .build(cx, |cx| start_refresh_timer(cx, CURVE_REFRESH))
+    }
+
+    fn db_to_position(db: f32) -> f32 {
+        ((db - CURVE_MIN_DB) / (CURVE_MAX_DB - CURVE_MIN_DB)).clamp(0.0, 1.0)
+    }
+
+    fn point(bounds: BoundingBox, input_db: f32, output_db: f32) -> (f32, f32) {
+        (
+            bounds.x + bounds.w * Self::db_to_position(input_db),
+            bounds.y + bounds.h * (1.0 - Self::db_to_position(output_db)),
+        )
+    }
+}
+
+impl View for TransferCurveView {
+    fn element(&self) -> Option<&'static str> {
+        Some("transfer-curve")
+    }
+
+    fn draw(&self, cx: &mut DrawContext, canvas: &mut Canvas) {
+        let bounds = cx.bounds();
+        if bounds.w == 0.0 || bounds.h == 0.0 {
+            return;
+        }
+        let palette = self.theme.palette();
+
+        draw_background(canvas, bounds, palette);
+
+        let threshold = self.params.threshold.value();
+        let ratio = self.params.ratio.value();
+
+        // The knee, shaded between its two ends
+        let (knee_start, _) = Self::point(bounds, threshold - KNEE_WIDTH_DB / 2.0, 0.0);
+        let (knee_end, _) = Self::point(bounds, threshold + KNEE_WIDTH_DB / 2.0, 0.0);
+        let mut knee = vg::Path::new();
+        knee.rect(knee_start, bounds.y, knee_end - knee_start, bounds.h);
+        canvas.fill_path(&knee, &vg::Paint::color(vg_color(palette.accent_muted)));
+
+        // Unity reference and the threshold
+        let mut grid = vg::Path::new();
+        let (start_x, start_y) = Self::point(bounds, CURVE_MIN_DB, CURVE_MIN_DB);
+        let (end_x, end_y) = Self::point(bounds, CURVE_MAX_DB, CURVE_MAX_DB);
+        grid.move_to(start_x, start_y);
+        grid.line_to(end_x, end_y);
+        let (threshold_x, _) = Self::point(bounds, threshold, 0.0);
+        grid.move_to(threshold_x, bounds.y);
+        grid.line_to(threshold_x, bounds.y + bounds.h);
+        canvas.stroke_path(&grid, &vg::Paint::color(vg_color(palette.border)));
+
+        let mut curve = vg::Path::new();
+        let mut x = 0.0;
+        while x <= bounds.w {
+            let input_db = CURVE_MIN_DB + (CURVE_MAX_DB - CURVE_MIN_DB) * x / bounds.w;
+            let output_db =
+                input_db + Compressor::static_gain_db(input_db, threshold, ratio, KNEE_WIDTH_DB);
+            let (px, py) = Self::point(bounds, input_db, output_db);
+            if x == 0.0 {
+                curve.move_to(px, py);
+            } else {
+                curve.line_to(px, py);
+            }
+            x += CURVE_STEP;
+        }
+        let mut paint = vg::Paint::color(vg_color(palette.highlight));
+        paint.set_line_width(2.0);
+        canvas.stroke_path(&curve, &paint);
+
+        // The dot trails the curve while the compressor's smoothing catches up
+        let (detector_db, gain_reduction) = self.meter.detector();
+        if detector_db > CURVE_MIN_DB {
+            let (dot_x, dot_y) = Self::point(bounds, detector_db, detector_db - gain_reduction);
+            let mut dot = vg::Path::new();
+            dot.circle(dot_x, dot_y, DOT_RADIUS);
+            canvas.fill_path(&dot, &vg::Paint::color(vg_color(palette.text)));
+        }
+    }
+}
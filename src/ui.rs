@@ -1,482 +1,604 @@
 use nih_plug::prelude::*;
 use nih_plug_vizia::vizia::prelude::*;
-use nih_plug_vizia::widgets::ParamSlider;
+use nih_plug_vizia::vizia::vg;
+use nih_plug_vizia::widgets::{ParamSlider, ResizeHandle};
 use nih_plug_vizia::{assets, create_vizia_editor, ViziaState, ViziaTheming};
 use std::sync::Arc;
+use std::time::Duration;
 
+use crate::ab_switch::{ab_bar, AbSwitch};
+use crate::colorizer::ColorType;
 use crate::device::KVPChannelPluginParams;
+use crate::frequency_response_view::FrequencyResponseView;
+use crate::gain_reduction_view::{GainReductionHistoryView, GainReductionView};
+use crate::knob::ParamKnob;
+use crate::level_meter_view::LevelMeterView;
+use crate::loudness::LoudnessMeter;
+use crate::loudness_view::{format_lufs, LoudnessView};
+use crate::meters::{LevelMeter, Meters};
+use crate::preset_browser::{preset_bar, PresetBrowser};
+use crate::saturation_view::SaturationView;
+use crate::theme::{self, EditorTheme, Palette};
+use crate::transfer_curve_view::TransferCurveView;
+
+const THEMES: [EditorTheme; 3] = [
+    EditorTheme::Rust,
+    EditorTheme::HighContrast,
+    EditorTheme::Light,
+];
+
+// UI scale factors offered in the footer
+const SCALE_PRESETS: [f64; 6] = [0.75, 1.0, 1.25, 1.5, 1.75, 2.0];
+
+// The theme colors are used fully opaque when drawing on the canvas
+pub(crate) fn vg_color(color: Color) -> vg::Color {
+    vg::Color::rgb(color.r(), color.g(), color.b())
+}
 
-// Define colors for our retrofuture rusted metal theme
-const RUST_ORANGE: Color = Color::rgba(194, 107, 36, 1);
-const RUST_ORANGE_DARK: Color = Color::rgba(150, 75, 20, 1);
-const RUST_ORANGE_LIGHT: Color = Color::rgba(226, 141, 63, 1);
-const PANEL_BG: Color = Color::rgba(40, 32, 28, 1);
-const TEXT_COLOR: Color = Color::rgba(255, 235, 210, 1);
-const BORDER_COLOR: Color = Color::rgba(90, 55, 30, 1);
+// The meters and displays read atomics and parameters the host can change at any time rather
+// than a model, so they poll them
+pub(crate) fn start_refresh_timer(cx: &mut Context, interval: Duration) {
+    let timer = cx.add_timer(interval, None, |cx, action| {
+        if let TimerAction::Tick(_) = action {
+            cx.needs_redraw();
+        }
+    });
+    cx.start_timer(timer);
+}
+
+// The panel colored, bordered rectangle every display draws on
+pub(crate) fn draw_background(canvas: &mut Canvas, bounds: BoundingBox, palette: &Palette) {
+    let mut background = vg::Path::new();
+    background.rect(bounds.x, bounds.y, bounds.w, bounds.h);
+    canvas.fill_path(&background, &vg::Paint::color(vg_color(palette.panel)));
+    canvas.stroke_path(&background, &vg::Paint::color(vg_color(palette.border)));
+}
 
 #[derive(Lens)]
 struct Data {
     params: Arc<KVPChannelPluginParams>,
+    // Re-read whenever the loudness view's refresh timer fires
+    loudness: Arc<LoudnessMeter>,
+    theme: EditorTheme,
 }
 
-impl Model for Data {}
+enum ThemeEvent {
+    Set(EditorTheme),
+}
+
+impl Model for Data {
+    fn event(&mut self, _cx: &mut EventContext, event: &mut Event) {
+        event.map(|theme_event, _| match *theme_event {
+            ThemeEvent::Set(theme) => {
+                self.theme = theme;
+                self.params.editor_theme.set_theme(theme);
+            }
+        });
+    }
+}
 
 // Main editor creation function that's called by the plugin
 pub(crate) fn create_editor(
     params: Arc<KVPChannelPluginParams>,
+    meters: Arc<Meters>,
     editor_state: Arc<ViziaState>,
 ) -> Option<Box<dyn Editor>> {
     create_vizia_editor(editor_state, ViziaTheming::Custom, move |ctx, _| {
         assets::register_noto_sans_regular(ctx);
         assets::register_noto_sans_bold(ctx);
+        ctx.add_stylesheet(include_style!("src/theme.css"))
+            .expect("Failed to load stylesheet");
+        ctx.add_stylesheet(theme::stylesheet())
+            .expect("Failed to load theme colors");
 
         Data {
             params: params.clone(),
+            loudness: meters.loudness.clone(),
+            theme: params.editor_theme.theme(),
         }
         .build(ctx);
+        PresetBrowser::new(params.clone()).build(ctx);
+        AbSwitch::new(params.clone(), meters.loudness.clone()).build(ctx);
 
-        VStack::new(ctx, |cx| {
-            // Header with title
-            Label::new(cx, "KVP CHANNEL")
-                .font_size(28.0)
-                .height(Pixels(50.0))
-                .color(RUST_ORANGE_LIGHT)
-                .text_align(TextAlign::Center);
+        let theme = params.editor_theme.clone();
+        let root = VStack::new(ctx, |cx| {
+            // Header with the presets, the title, the A/B switch and the theme menu
+            HStack::new(cx, |cx| {
+                preset_bar(cx)
+                    .width(Stretch(1.5))
+                    .left(Pixels(10.0));
+
+                Label::new(cx, "KVP CHANNEL")
+                    .class("title")
+                    .width(Stretch(1.2))
+                    .text_align(TextAlign::Center);
+
+                ab_bar(cx);
+
+                Dropdown::new(
+                    cx,
+                    |cx| Label::new(cx, Data::theme.map(|theme| theme.name().to_uppercase())),
+                    |cx| {
+                        for theme in THEMES {
+                            Label::new(cx, theme.name())
+                                .class("menu-item")
+                                .width(Stretch(1.0))
+                                .cursor(CursorIcon::Hand)
+                                .on_press(move |cx| {
+                                    cx.emit(ThemeEvent::Set(theme));
+                                    cx.emit(PopupEvent::Close);
+                                });
+                        }
+                    },
+                )
+                .class("theme-menu")
+                .width(Stretch(0.8))
+                .height(Pixels(22.0))
+                .right(Pixels(10.0));
+            })
+            .height(Pixels(36.0))
+            .child_top(Stretch(1.0))
+            .child_bottom(Stretch(1.0));
 
-            // Main container with three columns
+            // Levels at the input, between the devices and at the output
             HStack::new(cx, |cx| {
-                // Left Column - EQ
-                VStack::new(cx, |cx| {
-                    // EQ Header
-                    Label::new(cx, "EQ")
-                        .font_size(20.0)
-                        .height(Pixels(32.0))
-                        .color(TEXT_COLOR)
-                        .text_align(TextAlign::Center);
-
-                    // Input Gain
-                    VStack::new(cx, |cx| {
-                        Label::new(cx, "— INPUT GAIN —")
-                            .font_size(16.0)
-                            .height(Pixels(24.0))
-                            .color(TEXT_COLOR);
+                meter_group(cx, "IN", meters.input.clone());
+                meter_group(cx, "EQ", meters.post_eq.clone());
+                meter_group(cx, "COMP", meters.post_compressor.clone());
+                meter_group(cx, "OUT", meters.output.clone());
+            })
+            .height(Pixels(30.0))
+            .left(Pixels(10.0))
+            .right(Pixels(10.0));
+
+            // Output loudness after EBU R128
+            HStack::new(cx, |cx| {
+                Label::new(cx, "LUFS")
+                    .class("meter-label")
+                    .width(Pixels(40.0));
+
+                LoudnessView::new(cx, meters.loudness.clone(), params.clone(), theme.clone())
+                    .width(Stretch(4.0))
+                    .height(Pixels(20.0));
+
+                loudness_readout(cx, "M", |loudness| loudness.momentary());
+                loudness_readout(cx, "S", |loudness| loudness.short_term());
+                loudness_readout(cx, "I", |loudness| loudness.integrated());
+                Label::new(
+                    cx,
+                    Data::loudness.map(|loudness| format!("LRA {:.1}", loudness.range())),
+                )
+                .class("readout")
+                .width(Stretch(1.0))
+                .left(Pixels(6.0));
+
+                ParamSlider::new(cx, Data::params, |params| &params.loudness_target)
+                    .width(Stretch(1.6))
+                    .height(Pixels(20.0))
+                    .left(Pixels(6.0));
+
+                let loudness = meters.loudness.clone();
+                Label::new(cx, "RESET")
+                    .class("readout")
+                    .class("button")
+                    .width(Stretch(0.8))
+                    .left(Pixels(6.0))
+                    .text_align(TextAlign::Center)
+                    .cursor(CursorIcon::Hand)
+                    .on_press(move |_| loudness.request_reset());
+            })
+            .height(Pixels(26.0))
+            .left(Pixels(14.0))
+            .right(Pixels(14.0))
+            .child_top(Stretch(1.0))
+            .child_bottom(Stretch(1.0));
+
+            // Main container with the response across the top and three columns below it
+            VStack::new(cx, |cx| {
+                // Combined EQ response over the analyzer, with the analyzer display settings
+                HStack::new(cx, |cx| {
+                    FrequencyResponseView::new(cx, params.clone(), meters.clone(), theme.clone())
+                        .width(Stretch(1.0))
+                        .height(Stretch(1.0));
 
+                    VStack::new(cx, |cx| {
                         HStack::new(cx, |cx| {
-                            Label::new(cx, "Gain")
-                                .width(Pixels(80.0))
-                                .color(TEXT_COLOR);
-                            ParamSlider::new(cx, Data::params, |params| &params.eq_params.input_gain);
+                            ParamSlider::new(cx, Data::params, |params| &params.analyzer_params.resolution)
+                                .width(Stretch(1.0));
+                            ParamSlider::new(cx, Data::params, |params| &params.analyzer_params.averaging)
+                                .width(Stretch(1.0));
                         })
-                        .height(Pixels(32.0))
-                        .left(Pixels(8.0));
-                    })
-                    .background_color(PANEL_BG)
-                    .border_color(BORDER_COLOR)
-                    .border_width(Pixels(1.0))
-                    .border_radius(Pixels(6.0))
-                    .child_space(Stretch(1.0))
-                    .top(Pixels(5.0))
-                    .bottom(Pixels(5.0))
-                    .left(Pixels(5.0))
-                    .right(Pixels(5.0));
-
-                    // Input Filters
-                    VStack::new(cx, |cx| {
-                        Label::new(cx, "— INPUT FILTERS —")
-                            .font_size(16.0)
-                            .height(Pixels(24.0))
-                            .color(TEXT_COLOR);
-
-                        // Low Cut
-                        VStack::new(cx, |cx| {
-                            Label::new(cx, "Low Cut")
-                                .color(TEXT_COLOR)
-                                .height(Pixels(20.0))
-                                .text_align(TextAlign::Center);
-                                
-                            ParamSlider::new(cx, Data::params, |params| &params.eq_params.input_eq_highpass)
-                                .height(Pixels(32.0))
-                                .left(Pixels(8.0))
-                                .right(Pixels(8.0));
-
-                            ParamSlider::new(cx, Data::params, |params| &params.eq_params.input_eq_highpass_mode)
-                                .height(Pixels(25.0))
-                                .left(Pixels(8.0))
-                                .right(Pixels(8.0));
+                        .height(Pixels(20.0))
+                        .col_between(Pixels(4.0));
+
+                        HStack::new(cx, |cx| {
+                            ParamSlider::new(cx, Data::params, |params| &params.analyzer_params.slope)
+                                .width(Stretch(1.0));
+                            ParamSlider::new(cx, Data::params, |params| &params.analyzer_params.peak_hold)
+                                .width(Stretch(1.0));
                         })
-                        .top(Pixels(5.0));
-
-                        // High Cut
-                        VStack::new(cx, |cx| {
-                            Label::new(cx, "High Cut")
-                                .color(TEXT_COLOR)
-                                .height(Pixels(20.0))
-                                .text_align(TextAlign::Center);
-                                
-                            ParamSlider::new(cx, Data::params, |params| &params.eq_params.input_eq_lowpass)
-                                .height(Pixels(32.0))
-                                .left(Pixels(8.0))
-                                .right(Pixels(8.0));
-
-                            ParamSlider::new(cx, Data::params, |params| &params.eq_params.input_eq_lowpass_mode)
-                                .height(Pixels(25.0))
-                                .left(Pixels(8.0))
-                                .right(Pixels(8.0));
+                        .height(Pixels(20.0))
+                        .col_between(Pixels(4.0));
+
+                        HStack::new(cx, |cx| {
+                            ParamSlider::new(cx, Data::params, |params| &params.analyzer_params.freeze)
+                                .width(Stretch(1.0));
+                            Element::new(cx).width(Stretch(1.0));
                         })
-                        .top(Pixels(10.0))
-                        .bottom(Pixels(5.0));
+                        .height(Pixels(20.0))
+                        .col_between(Pixels(4.0));
                     })
-                    .background_color(PANEL_BG)
-                    .border_color(BORDER_COLOR)
-                    .border_width(Pixels(1.0))
-                    .border_radius(Pixels(6.0))
-                    .child_space(Stretch(1.0))
-                    .top(Pixels(5.0))
-                    .bottom(Pixels(5.0))
-                    .left(Pixels(5.0))
-                    .right(Pixels(5.0));
-
-                    // Pull Section
+                    .width(Pixels(230.0))
+                    .row_between(Pixels(4.0))
+                    .child_top(Stretch(1.0))
+                    .child_bottom(Stretch(1.0));
+                })
+                .height(Pixels(90.0))
+                .col_between(Pixels(6.0))
+                .bottom(Pixels(6.0));
+
+                HStack::new(cx, |cx| {
+                    // Left Column - Input trim and Pull EQ
                     VStack::new(cx, |cx| {
-                        Label::new(cx, "— PULL EQ —")
-                            .font_size(16.0)
-                            .height(Pixels(24.0))
-                            .color(TEXT_COLOR);
-
-                        // Low Shelf
-                        VStack::new(cx, |cx| {
-                            Label::new(cx, "Low Shelf")
-                                .color(TEXT_COLOR)
-                                .height(Pixels(20.0))
-                                .text_align(TextAlign::Center);
-                                
+                        panel(cx, "INPUT", |cx| {
                             HStack::new(cx, |cx| {
-                                Label::new(cx, "Freq")
-                                    .width(Pixels(40.0))
-                                    .color(TEXT_COLOR);
-                                ParamSlider::new(cx, Data::params, |params| &params.eq_params.pull_lowshelf);
+                                knob(cx, "Gain", |params| &params.eq_params.input_gain);
+                                knob(cx, "Low Cut", |params| &params.eq_params.input_eq_highpass);
+                                knob(cx, "High Cut", |params| &params.eq_params.input_eq_lowpass);
                             })
-                            .height(Pixels(32.0))
-                            .left(Pixels(8.0));
+                            .height(Auto);
 
                             HStack::new(cx, |cx| {
-                                Label::new(cx, "Gain")
-                                    .width(Pixels(40.0))
-                                    .color(TEXT_COLOR);
-                                ParamSlider::new(cx, Data::params, |params| &params.eq_params.pull_lowshelf_gain);
-                            })
-                            .height(Pixels(32.0))
-                            .left(Pixels(8.0));
-                        })
-                        .bottom(Pixels(8.0));
-
-                        // Low Pull
-                        VStack::new(cx, |cx| {
-                            Label::new(cx, "Low Pull")
-                                .color(TEXT_COLOR)
-                                .height(Pixels(20.0))
-                                .text_align(TextAlign::Center);
-                                
-                            HStack::new(cx, |cx| {
-                                Label::new(cx, "Freq")
-                                    .width(Pixels(40.0))
-                                    .color(TEXT_COLOR);
-                                ParamSlider::new(cx, Data::params, |params| &params.eq_params.pull_lowpull);
+                                ParamSlider::new(cx, Data::params, |params| &params.eq_params.input_eq_highpass_mode)
+                                    .width(Stretch(1.0));
+                                ParamSlider::new(cx, Data::params, |params| &params.eq_params.input_eq_lowpass_mode)
+                                    .width(Stretch(1.0));
                             })
-                            .height(Pixels(32.0))
-                            .left(Pixels(8.0));
+                            .height(Pixels(20.0))
+                            .col_between(Pixels(4.0))
+                            .top(Pixels(2.0));
+                        });
 
+                        panel(cx, "PULL EQ", |cx| {
                             HStack::new(cx, |cx| {
-                                Label::new(cx, "Gain")
-                                    .width(Pixels(40.0))
-                                    .color(TEXT_COLOR);
-                                ParamSlider::new(cx, Data::params, |params| &params.eq_params.pull_lowpull_gain);
+                                knob(cx, "Shelf", |params| &params.eq_params.pull_lowshelf);
+                                knob(cx, "Gain", |params| &params.eq_params.pull_lowshelf_gain);
+                                knob(cx, "Q", |params| &params.eq_params.pull_lowshelf_q);
                             })
-                            .height(Pixels(32.0))
-                            .left(Pixels(8.0));
-                        })
-                        .bottom(Pixels(8.0));
-
-                        // High Pull
-                        VStack::new(cx, |cx| {
-                            Label::new(cx, "High Pull")
-                                .color(TEXT_COLOR)
-                                .height(Pixels(20.0))
-                                .text_align(TextAlign::Center);
-                                
+                            .height(Auto);
+
                             HStack::new(cx, |cx| {
-                                Label::new(cx, "Freq")
-                                    .width(Pixels(40.0))
-                                    .color(TEXT_COLOR);
-                                ParamSlider::new(cx, Data::params, |params| &params.eq_params.pull_highpull);
+                                knob(cx, "Low Pull", |params| &params.eq_params.pull_lowpull);
+                                knob(cx, "Gain", |params| &params.eq_params.pull_lowpull_gain);
+                                knob(cx, "Q", |params| &params.eq_params.pull_lowpull_q);
                             })
-                            .height(Pixels(32.0))
-                            .left(Pixels(8.0));
+                            .height(Auto);
 
                             HStack::new(cx, |cx| {
-                                Label::new(cx, "Gain")
-                                    .width(Pixels(40.0))
-                                    .color(TEXT_COLOR);
-                                ParamSlider::new(cx, Data::params, |params| &params.eq_params.pull_highpull_gain);
+                                knob(cx, "High Pull", |params| &params.eq_params.pull_highpull);
+                                knob(cx, "Gain", |params| &params.eq_params.pull_highpull_gain);
+                                knob(cx, "Q", |params| &params.eq_params.pull_highpull_q);
                             })
-                            .height(Pixels(32.0))
-                            .left(Pixels(8.0));
+                            .height(Auto);
                         });
                     })
-                    .background_color(PANEL_BG)
-                    .border_color(BORDER_COLOR)
-                    .border_width(Pixels(1.0))
-                    .border_radius(Pixels(6.0))
-                    .child_space(Stretch(1.0))
-                    .top(Pixels(5.0))
-                    .bottom(Pixels(5.0))
-                    .left(Pixels(5.0))
-                    .right(Pixels(5.0));
-                })
-                .width(Percentage(34.0))
-                .left(Pixels(5.0))
-                .right(Pixels(5.0));
+                    .width(Stretch(1.0));
 
-                // Middle Column - Push EQ and Compressor
-                VStack::new(cx, |cx| {
-                    // Push Section
+                    // Middle Column - Push EQ and Compressor
                     VStack::new(cx, |cx| {
-                        Label::new(cx, "— PUSH EQ —")
-                            .font_size(16.0)
-                            .height(Pixels(24.0))
-                            .color(TEXT_COLOR);
-
-                        // Push Gain
-                        VStack::new(cx, |cx| {
-                            Label::new(cx, "Output Gain")
-                                .color(TEXT_COLOR)
-                                .height(Pixels(20.0))
-                                .text_align(TextAlign::Center);
-                                
-                            ParamSlider::new(cx, Data::params, |params| &params.eq_params.push_gain)
-                                .height(Pixels(32.0))
-                                .left(Pixels(8.0))
-                                .right(Pixels(8.0));
-                        })
-                        .bottom(Pixels(8.0));
-
-                        // Overtone Push
-                        VStack::new(cx, |cx| {
-                            Label::new(cx, "Overtone")
-                                .color(TEXT_COLOR)
-                                .height(Pixels(20.0))
-                                .text_align(TextAlign::Center);
-                                
+                        panel(cx, "PUSH EQ", |cx| {
                             HStack::new(cx, |cx| {
-                                Label::new(cx, "Freq")
-                                    .width(Pixels(40.0))
-                                    .color(TEXT_COLOR);
-                                ParamSlider::new(cx, Data::params, |params| &params.eq_params.push_overtone_push);
+                                knob(cx, "Overtone", |params| &params.eq_params.push_overtone_push);
+                                knob(cx, "Gain", |params| &params.eq_params.push_overtone_push_gain);
+                                knob(cx, "Q", |params| &params.eq_params.push_overtone_push_q);
+                                knob(cx, "Output", |params| &params.eq_params.push_gain);
                             })
-                            .height(Pixels(32.0))
-                            .left(Pixels(8.0));
+                            .height(Auto);
 
                             HStack::new(cx, |cx| {
-                                Label::new(cx, "Gain")
-                                    .width(Pixels(40.0))
-                                    .color(TEXT_COLOR);
-                                ParamSlider::new(cx, Data::params, |params| &params.eq_params.push_overtone_push_gain);
+                                knob(cx, "Tonal", |params| &params.eq_params.push_tonal_push);
+                                knob(cx, "Gain", |params| &params.eq_params.push_tonal_push_gain);
+                                knob(cx, "Q", |params| &params.eq_params.push_tonal_push_q);
+                                // Keeps the knobs in line with the row above
+                                Element::new(cx).width(Stretch(1.0));
                             })
-                            .height(Pixels(32.0))
-                            .left(Pixels(8.0));
-                        })
-                        .bottom(Pixels(8.0));
-
-                        // Tonal Push
-                        VStack::new(cx, |cx| {
-                            Label::new(cx, "Tonal")
-                                .color(TEXT_COLOR)
-                                .height(Pixels(20.0))
-                                .text_align(TextAlign::Center);
-                                
+                            .height(Auto);
+                        });
+
+                        panel(cx, "COMPRESSOR", |cx| {
                             HStack::new(cx, |cx| {
-                                Label::new(cx, "Freq")
-                                    .width(Pixels(40.0))
-                                    .color(TEXT_COLOR);
-                                ParamSlider::new(cx, Data::params, |params| &params.eq_params.push_tonal_push);
+                                ParamSlider::new(cx, Data::params, |params| &params.compressor_params.preset)
+                                    .width(Stretch(1.0));
+                                ParamSlider::new(cx, Data::params, |params| &params.compressor_params.ratio)
+                                    .width(Stretch(1.0));
                             })
-                            .height(Pixels(32.0))
-                            .left(Pixels(8.0));
+                            .height(Pixels(20.0))
+                            .col_between(Pixels(4.0));
 
+                            // Threshold next to the static curve with the live detector level
                             HStack::new(cx, |cx| {
-                                Label::new(cx, "Gain")
-                                    .width(Pixels(40.0))
-                                    .color(TEXT_COLOR);
-                                ParamSlider::new(cx, Data::params, |params| &params.eq_params.push_tonal_push_gain);
+                                knob(cx, "Threshold", |params| &params.compressor_params.threshold)
+                                    .width(Pixels(64.0));
+
+                                TransferCurveView::new(
+                                    cx,
+                                    params.compressor_params.clone(),
+                                    meters.gain_reduction.clone(),
+                                    theme.clone(),
+                                )
+                                .width(Stretch(1.0))
+                                .height(Pixels(60.0))
+                                .left(Pixels(4.0));
                             })
-                            .height(Pixels(32.0))
-                            .left(Pixels(8.0));
+                            .height(Auto)
+                            .top(Pixels(4.0));
+
+                            // Gain reduction
+                            GainReductionView::new(cx, meters.gain_reduction.clone(), theme.clone())
+                                .height(Pixels(12.0))
+                                .top(Pixels(4.0));
+
+                            GainReductionHistoryView::new(cx, meters.gain_reduction.clone(), theme.clone())
+                                .height(Pixels(30.0))
+                                .top(Pixels(2.0));
                         });
                     })
-                    .background_color(PANEL_BG)
-                    .border_color(BORDER_COLOR)
-                    .border_width(Pixels(1.0))
-                    .border_radius(Pixels(6.0))
-                    .child_space(Stretch(1.0))
-                    .top(Pixels(37.0)) // Align with first section header in left column
-                    .bottom(Pixels(5.0))
-                    .left(Pixels(5.0))
-                    .right(Pixels(5.0));
-
-                    // Compressor Section
-                    VStack::new(cx, |cx| {
-                        // Section Header
-                        Label::new(cx, "COMPRESSOR")
-                            .font_size(20.0)
-                            .height(Pixels(32.0))
-                            .color(TEXT_COLOR)
-                            .text_align(TextAlign::Center);
-
-                        // Preset
-                        Label::new(cx, "Preset")
-                            .font_size(16.0)
-                            .height(Pixels(24.0))
-                            .color(TEXT_COLOR);
-
-                        ParamSlider::new(cx, Data::params, |params| &params.compressor_params.preset)
-                            .height(Pixels(32.0))
-                            .left(Pixels(8.0))
-                            .right(Pixels(8.0));
-
-                        // Threshold
-                        Label::new(cx, "Threshold")
-                            .font_size(16.0)
-                            .height(Pixels(24.0))
-                            .color(TEXT_COLOR)
-                            .top(Pixels(15.0));
-
-                        ParamSlider::new(cx, Data::params, |params| &params.compressor_params.threshold)
-                            .height(Pixels(32.0))
-                            .left(Pixels(8.0))
-                            .right(Pixels(8.0));
-
-                        // Ratio
-                        Label::new(cx, "Ratio")
-                            .font_size(16.0)
-                            .height(Pixels(24.0))
-                            .color(TEXT_COLOR)
-                            .top(Pixels(15.0));
-
-                        ParamSlider::new(cx, Data::params, |params| &params.compressor_params.ratio)
-                            .height(Pixels(32.0))
-                            .left(Pixels(8.0))
-                            .right(Pixels(8.0));
-                    })
-                    .background_color(PANEL_BG)
-                    .border_color(BORDER_COLOR)
-                    .border_width(Pixels(1.0))
-                    .border_radius(Pixels(6.0))
-                    .child_space(Stretch(1.0))
-                    .top(Pixels(5.0))
-                    .bottom(Pixels(5.0))
-                    .left(Pixels(5.0))
-                    .right(Pixels(5.0));
-                })
-                .width(Percentage(33.0))
-                .left(Pixels(5.0))
-                .right(Pixels(5.0));
-
-                // Right Column - Colorizer
-                VStack::new(cx, |cx| {
-                    // Section Header
-                    Label::new(cx, "COLORIZER")
-                        .font_size(20.0)
-                        .height(Pixels(32.0))
-                        .color(TEXT_COLOR)
-                        .text_align(TextAlign::Center);
+                    .width(Stretch(1.0));
 
-                    VStack::new(cx, |cx| {
-                        // Type
-                        Label::new(cx, "Type")
-                            .font_size(16.0)
-                            .height(Pixels(24.0))
-                            .color(TEXT_COLOR);
-
-                        ParamSlider::new(cx, Data::params, |params| &params.colorizer_params.color_type)
-                            .height(Pixels(32.0))
-                            .left(Pixels(8.0))
-                            .right(Pixels(8.0));
-
-                        // Intensity
-                        Label::new(cx, "Intensity")
-                            .font_size(16.0)
-                            .height(Pixels(24.0))
-                            .color(TEXT_COLOR)
-                            .top(Pixels(15.0));
-
-                        ParamSlider::new(cx, Data::params, |params| &params.colorizer_params.intensity)
-                            .height(Pixels(32.0))
-                            .left(Pixels(8.0))
-                            .right(Pixels(8.0));
-                            
-                        // Decorative knob visualization
-                        Label::new(cx, "COLOR TONE")
-                            .font_size(14.0)
-                            .height(Pixels(20.0))
-                            .color(TEXT_COLOR)
-                            .text_align(TextAlign::Center)
-                            .top(Pixels(30.0));
-                            
-                        Element::new(cx)
-                            .background_color(RUST_ORANGE)
-                            .width(Pixels(100.0))
-                            .height(Pixels(100.0))
-                            .border_radius(Percentage(50.0))
-                            .border_color(BORDER_COLOR)
-                            .border_width(Pixels(2.0))
-                            .top(Pixels(10.0));
-                            
-                        // Visual EQ display representation
-                        Label::new(cx, "FREQUENCY RESPONSE")
-                            .font_size(14.0)
-                            .height(Pixels(20.0))
-                            .color(TEXT_COLOR)
-                            .text_align(TextAlign::Center)
-                            .top(Pixels(30.0));
-                            
-                        Element::new(cx)
-                            .background_color(PANEL_BG)
-                            .width(Percentage(90.0))
-                            .height(Pixels(120.0))
-                            .border_radius(Pixels(4.0))
-                            .border_color(BORDER_COLOR)
-                            .border_width(Pixels(1.0))
-                            .top(Pixels(5.0));
+                    // Right Column - Colorizer
+                    panel(cx, "COLORIZER", |cx| {
+                        HStack::new(cx, |cx| {
+                            ParamSlider::new(cx, Data::params, |params| &params.colorizer_params.color_type)
+                                .width(Stretch(1.0));
+                            ParamSlider::new(cx, Data::params, |params| &params.colorizer_params.auto_gain)
+                                .width(Stretch(1.0));
+                        })
+                        .height(Pixels(20.0))
+                        .col_between(Pixels(4.0));
+
+                        HStack::new(cx, |cx| {
+                            knob(cx, "Intensity", |params| &params.colorizer_params.intensity);
+                            knob(cx, "Drive", |params| &params.colorizer_params.drive);
+                            knob(cx, "Saturation", |params| &params.colorizer_params.saturation);
+                            // Tone tilts the saturation dark or bright
+                            knob(cx, "Tone", |params| &params.colorizer_params.tone);
+                        })
+                        .height(Auto)
+                        .top(Pixels(4.0));
+
+                        HStack::new(cx, |cx| {
+                            knob(cx, "Output", |params| &params.colorizer_params.output);
+                            knob(cx, "Mix", |params| &params.colorizer_params.mix);
+                            knob(cx, "Variance", |params| &params.colorizer_params.analog_variance);
+
+                            // Transfer curve and the harmonics it adds
+                            SaturationView::new(
+                                cx,
+                                params.colorizer_params.clone(),
+                                meters.clone(),
+                                theme.clone(),
+                            )
+                            .width(Stretch(1.6))
+                            .height(Pixels(56.0))
+                            .left(Pixels(4.0));
+                        })
+                        .height(Auto);
+
+                        // Oversampling
+                        HStack::new(cx, |cx| {
+                            ParamSlider::new(cx, Data::params, |params| &params.colorizer_params.oversampling)
+                                .width(Stretch(1.0));
+                            ParamSlider::new(cx, Data::params, |params| &params.colorizer_params.oversampling_phase)
+                                .width(Stretch(1.0));
+                            ParamSlider::new(cx, Data::params, |params| &params.colorizer_params.antialiasing)
+                                .width(Stretch(1.0));
+                        })
+                        .height(Pixels(20.0))
+                        .col_between(Pixels(4.0))
+                        .top(Pixels(4.0));
+
+                        // Only the controls of the selected color type are shown so everything
+                        // fits without scrolling
+                        Binding::new(
+                            cx,
+                            Data::params.map(|params| params.colorizer_params.color_type.value().to_index()),
+                            |cx, color_type| color_type_section(cx, ColorType::from_index(color_type.get(cx))),
+                        );
                     })
-                    .background_color(PANEL_BG)
-                    .border_color(BORDER_COLOR)
-                    .border_width(Pixels(1.0))
-                    .border_radius(Pixels(6.0))
-                    .child_space(Stretch(1.0))
-                    .top(Pixels(37.0)) // Align with first section in left column
-                    .bottom(Pixels(5.0))
-                    .left(Pixels(5.0))
-                    .right(Pixels(5.0))
-                    .height(Percentage(95.0));
+                    .width(Stretch(1.0));
                 })
-                .width(Percentage(33.0))
-                .left(Pixels(5.0))
-                .right(Pixels(5.0));
+                .height(Stretch(1.0))
+                .col_between(Pixels(6.0));
             })
-            .height(Percentage(90.0))
-            .child_space(Stretch(1.0))
-            .top(Pixels(10.0))
-            .bottom(Pixels(10.0))
-            .left(Pixels(10.0))
-            .right(Pixels(10.0));
-            
+            .height(Stretch(1.0))
+            .left(Pixels(8.0))
+            .right(Pixels(8.0))
+            .top(Pixels(4.0))
+            .bottom(Pixels(4.0));
+
             // Footer
-            Label::new(cx, "KVP STUDIOS © 2025")
-                .font_size(12.0)
-                .color(RUST_ORANGE_LIGHT)
-                .text_align(TextAlign::Center)
-                .height(Pixels(20.0));
+            HStack::new(cx, |cx| {
+                Label::new(cx, "KVP STUDIOS © 2025")
+                    .class("brand")
+                    .text_align(TextAlign::Center)
+                    .width(Stretch(1.4));
+
+                // UI scale, the resize handle in the corner covers everything in between
+                for scale in SCALE_PRESETS {
+                    Label::new(cx, &format!("{:.0}%", scale * 100.0))
+                        .class("scale-preset")
+                        .width(Stretch(0.45))
+                        .text_align(TextAlign::Center)
+                        .cursor(CursorIcon::Hand)
+                        .on_press(move |cx| cx.set_user_scale_factor(scale));
+                }
+
+                // Console mode
+                ParamSlider::new(cx, Data::params, |params| &params.console_params.enabled)
+                    .width(Stretch(1.0))
+                    .left(Pixels(8.0));
+                ParamSlider::new(cx, Data::params, |params| &params.console_params.crosstalk)
+                    .width(Stretch(1.0));
+                ParamSlider::new(cx, Data::params, |params| &params.console_params.summing)
+                    .width(Stretch(1.0));
+
+                ParamSlider::new(cx, Data::params, |params| &params.output_dc_block)
+                    .width(Stretch(1.1))
+                    .left(Pixels(8.0));
+            })
+            .height(Pixels(20.0))
+            .col_between(Pixels(4.0))
+            .right(Pixels(22.0))
+            .bottom(Pixels(4.0));
+
+            // Dragging the corner scales the whole editor. This has to be the last element so it
+            // receives the mouse events before anything underneath it
+            ResizeHandle::new(cx)
+                .position_type(PositionType::SelfDirected)
+                .left(Stretch(1.0))
+                .top(Stretch(1.0))
+                .width(Pixels(16.0))
+                .height(Pixels(16.0));
         })
-        .background_color(RUST_ORANGE_DARK);
+        .class("editor");
+
+        // The stylesheet keys every color on the theme's class
+        THEMES.iter().fold(root, |root, &theme| {
+            root.toggle_class(theme.class(), Data::theme.map(move |current| *current == theme))
+        });
+    })
+}
+
+// A titled panel in one of the columns
+fn panel(cx: &mut Context, title: &str, content: impl FnOnce(&mut Context)) -> Handle<VStack> {
+    VStack::new(cx, |cx| {
+        Label::new(cx, title)
+            .class("panel-title")
+            .height(Pixels(18.0))
+            .width(Stretch(1.0))
+            .text_align(TextAlign::Center);
+
+        content(cx);
     })
-}
\ No newline at end of file
+    .class("panel")
+    .height(Auto)
+    .child_space(Pixels(5.0))
+    .bottom(Pixels(6.0))
+}
+
+// A small heading between groups of controls inside a panel
+fn section_label(cx: &mut Context, label: &str) {
+    Label::new(cx, label)
+        .class("section-label")
+        .height(Pixels(16.0))
+        .top(Pixels(6.0));
+}
+
+// The controls only one color type uses, under a heading with its name
+fn color_type_section(cx: &mut Context, color_type: ColorType) {
+    match color_type {
+        ColorType::Exciter => {
+            section_label(cx, "Exciter");
+
+            HStack::new(cx, |cx| {
+                knob(cx, "Freq", |params| &params.colorizer_params.exciter_frequency);
+                knob(cx, "Amount", |params| &params.colorizer_params.exciter_amount);
+                knob(cx, "Harmonics", |params| &params.colorizer_params.exciter_harmonics);
+            })
+            .height(Auto);
+        }
+        ColorType::LoFi => {
+            section_label(cx, "Lo-Fi");
+
+            HStack::new(cx, |cx| {
+                ParamSlider::new(cx, Data::params, |params| &params.colorizer_params.lofi_filter)
+                    .width(Stretch(1.0));
+                ParamSlider::new(cx, Data::params, |params| &params.colorizer_params.lofi_dither)
+                    .width(Stretch(1.0));
+            })
+            .height(Pixels(20.0))
+            .col_between(Pixels(4.0));
+
+            HStack::new(cx, |cx| {
+                knob(cx, "Bits", |params| &params.colorizer_params.lofi_bits);
+                knob(cx, "Rate", |params| &params.colorizer_params.lofi_rate);
+                knob(cx, "Crackle", |params| &params.colorizer_params.lofi_crackle);
+            })
+            .height(Auto)
+            .top(Pixels(4.0));
+        }
+        ColorType::Tape => {
+            section_label(cx, "Tape");
+
+            HStack::new(cx, |cx| {
+                ParamSlider::new(cx, Data::params, |params| &params.colorizer_params.tape_speed)
+                    .width(Stretch(1.0));
+                ParamSlider::new(cx, Data::params, |params| &params.colorizer_params.tape_hiss)
+                    .width(Stretch(1.0));
+            })
+            .height(Pixels(20.0))
+            .col_between(Pixels(4.0));
+
+            HStack::new(cx, |cx| {
+                knob(cx, "Wow", |params| &params.colorizer_params.tape_wow);
+                knob(cx, "Flutter", |params| &params.colorizer_params.tape_flutter);
+                knob(cx, "Hiss", |params| &params.colorizer_params.tape_hiss_level);
+            })
+            .height(Auto)
+            .top(Pixels(4.0));
+        }
+        _ => {}
+    }
+}
+
+// A knob with the parameter's short name above it, sharing its row evenly with its siblings
+fn knob<P, FMap>(cx: &mut Context, label: &str, params_to_param: FMap) -> Handle<VStack>
+where
+    P: Param + 'static,
+    FMap: Fn(&Arc<KVPChannelPluginParams>) -> &P + Copy + 'static,
+{
+    VStack::new(cx, |cx| {
+        Label::new(cx, label)
+            .class("knob-label")
+            .height(Pixels(12.0))
+            .width(Stretch(1.0))
+            .text_align(TextAlign::Center);
+
+        let theme = Data::params.get(cx).editor_theme.clone();
+        ParamKnob::new(cx, Data::params, params_to_param, theme)
+            .width(Stretch(1.0))
+            .height(Pixels(44.0));
+    })
+    .width(Stretch(1.0))
+    .height(Auto)
+}
+
+// A labelled level meter for one point in the chain
+fn meter_group(cx: &mut Context, label: &str, meter: Arc<LevelMeter>) {
+    HStack::new(cx, |cx| {
+        Label::new(cx, label)
+            .class("meter-label")
+            .width(Pixels(40.0));
+
+        let theme = Data::params.get(cx).editor_theme.clone();
+        LevelMeterView::new(cx, meter, theme)
+            .width(Stretch(1.0))
+            .height(Pixels(28.0));
+    })
+    .width(Stretch(1.0))
+    .left(Pixels(4.0))
+    .right(Pixels(4.0))
+    .child_top(Stretch(1.0))
+    .child_bottom(Stretch(1.0));
+}
+
+// A labelled numeric loudness reading
+fn loudness_readout(cx: &mut Context, label: &'static str, reading: fn(&LoudnessMeter) -> f32) {
+    Label::new(
+        cx,
+        Data::loudness.map(move |loudness| format!("{label} {}", format_lufs(reading(loudness)))),
+    )
+    .class("readout")
+    .width(Stretch(1.0))
+    .left(Pixels(6.0));
+}